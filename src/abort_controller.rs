@@ -1,5 +1,8 @@
-type ListenerFn = Box<dyn Fn(Option<String>)>;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use crate::event_target::{Event, EventTarget};
 
+#[derive(Default)]
 pub struct AbortController {
   pub signal: AbortSignal,
 }
@@ -11,41 +14,80 @@ impl AbortController {
     }
   }
 
-  pub fn abort(&mut self, reason: Option<String>) -> () {
+  pub fn abort(&self, reason: Option<String>) {
     self.signal.abort(reason);
   }
 }
 
+#[derive(Default)]
+struct AbortState {
+  aborted: bool,
+  reason: Option<String>,
+}
+
+/// 中止信号，在`AbortController::abort`时分发`abort`事件
+///
+/// `AbortSignal`内部共享状态，克隆后的实例观察同一个中止状态，
+/// 可以通过`Deref`使用`EventTarget`的全部方法
+///
+/// # Example
+/// ```
+/// use fetch_js::abort_controller::AbortController;
+/// use fetch_js::event_target::AddEventListenerOptions;
+///
+/// let controller = AbortController::new();
+/// let signal = controller.signal.clone();
+/// signal.add_event_listener("abort", |event| {
+///   println!("aborted at {:?}", event.get_time_stamp());
+/// }, AddEventListenerOptions::default());
+/// controller.abort(Some("timeout".to_string()));
+/// assert!(signal.aborted());
+/// assert_eq!(signal.reason(), Some("timeout".to_string()));
+/// ```
+#[derive(Clone, Default)]
 pub struct AbortSignal {
-  pub aborted: bool,
-  pub reason: Option<String>,
-  listeners: Vec<ListenerFn>,
+  target: EventTarget,
+  state: Arc<Mutex<AbortState>>,
 }
 
 impl AbortSignal {
   pub fn new() -> Self {
-    Self {
-      aborted: false,
-      listeners: Vec::new(),
-      reason: None,
-    }
+    Self::default()
   }
 
-  pub fn add_event_listener(&mut self, listener: ListenerFn) -> usize {
-    self.listeners.push(listener);
-    self.listeners.len() - 1
+  pub fn aborted(&self) -> bool {
+    self.state.lock().unwrap().aborted
   }
-  
-  pub fn remove_event_listener(&mut self, index: usize) {
-    let _ = self.listeners.remove(index);
+
+  pub fn reason(&self) -> Option<String> {
+    self.state.lock().unwrap().reason.clone()
   }
-  
-  pub(in self) fn abort(&mut self, reason: Option<String>) -> () {
-    self.aborted = true;
-    self.reason = reason;
-    for listener in self.listeners.iter_mut() {
-      listener(self.reason.clone());
+
+  fn abort(&self, reason: Option<String>) {
+    {
+      let mut state = self.state.lock().unwrap();
+      if state.aborted {
+        return;
+      }
+      state.aborted = true;
+      state.reason = reason.clone();
     }
+    self.target.dispatch_event(&mut Event::new("abort").with_detail(reason));
   }
 }
 
+impl Deref for AbortSignal {
+  type Target = EventTarget;
+  fn deref(&self) -> &Self::Target {
+    &self.target
+  }
+}
+
+impl std::fmt::Debug for AbortSignal {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("AbortSignal")
+      .field("aborted", &self.aborted())
+      .field("reason", &self.reason())
+      .finish()
+  }
+}
@@ -0,0 +1,65 @@
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// 标准base64编码，带`=`填充
+pub(crate) fn encode(input: &[u8]) -> String {
+  let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+  for chunk in input.chunks(3) {
+    let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+    let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+    output.push(ALPHABET[(n >> 18) as usize & 63] as char);
+    output.push(ALPHABET[(n >> 12) as usize & 63] as char);
+    output.push(if chunk.len() > 1 { ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+    output.push(if chunk.len() > 2 { ALPHABET[n as usize & 63] as char } else { '=' });
+  }
+  output
+}
+
+/// 按照Infra规范的forgiving-base64解码，忽略ASCII空白，填充可以省略，格式错误时返回`None`
+pub(crate) fn decode(input: &[u8]) -> Option<Vec<u8>> {
+  let mut data: Vec<u8> = input.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+  if data.len().is_multiple_of(4) {
+    let padding = data.iter().rev().take(2).take_while(|b| **b == b'=').count();
+    data.truncate(data.len() - padding);
+  }
+  if data.len() % 4 == 1 {
+    return None;
+  }
+  let mut output = Vec::with_capacity(data.len() / 4 * 3 + 2);
+  let mut buffer = 0u32;
+  let mut bits = 0;
+  for byte in data {
+    let value = ALPHABET.iter().position(|c| *c == byte)? as u32;
+    buffer = buffer << 6 | value;
+    bits += 6;
+    if bits >= 8 {
+      bits -= 8;
+      output.push((buffer >> bits) as u8);
+    }
+  }
+  Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn encode_padding() {
+    assert_eq!(encode(b""), "");
+    assert_eq!(encode(b"f"), "Zg==");
+    assert_eq!(encode(b"fo"), "Zm8=");
+    assert_eq!(encode(b"foo"), "Zm9v");
+    assert_eq!(encode(b"user:pass"), "dXNlcjpwYXNz");
+  }
+
+  #[test]
+  fn forgiving_decode() {
+    assert_eq!(decode(b"Zm9v").unwrap(), b"foo");
+    assert_eq!(decode(b"Zg==").unwrap(), b"f");
+    assert_eq!(decode(b" Zm 8\n").unwrap(), b"fo");
+    assert_eq!(decode(b"").unwrap(), b"");
+    assert!(decode(b"Zg=").is_none());
+    assert!(decode(b"Zm9vY").is_none());
+    assert!(decode(b"Zm9v!").is_none());
+  }
+}
@@ -0,0 +1,194 @@
+mod file;
+
+pub use file::*;
+
+use std::io;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use crate::streams::ReadableStream;
+
+/// 对应JS中的`Blob`，保存不可变的二进制数据和MIME类型
+///
+/// 数据可以在内存中，也可以是磁盘上文件的一段，后者在读取时才会打开文件
+///
+/// # Example
+/// ```
+/// use fetch_js::blob::Blob;
+/// let blob = Blob::new("hello", "Text/Plain");
+/// assert_eq!(blob.size(), 5);
+/// assert_eq!(blob.get_type(), "text/plain");
+///
+/// let slice = blob.slice(Some(1), Some(-1), None);
+/// assert_eq!(slice.get_type(), "");
+/// # futures_util::FutureExt::now_or_never(async {
+/// assert_eq!(blob.bytes().await.unwrap(), "hello".as_bytes());
+/// assert_eq!(slice.text().await.unwrap(), "ell");
+/// # }).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Blob {
+  source: BlobSource,
+  mime_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BlobSource {
+  Bytes(Bytes),
+  #[cfg(feature = "tokio-fetch")]
+  Path {
+    path: std::sync::Arc<std::path::Path>,
+    /// 创建时文件的修改时间，读取时文件已被修改则返回错误
+    modified: Option<std::time::SystemTime>,
+    offset: u64,
+    length: u64,
+  },
+}
+
+impl Default for BlobSource {
+  fn default() -> Self {
+    BlobSource::Bytes(Bytes::new())
+  }
+}
+
+impl Blob {
+  /// 创建`Blob`，MIME类型会被转为小写，含有非ASCII可见字符时视为空类型
+  pub fn new(bytes: impl Into<Bytes>, mime_type: &str) -> Self {
+    Self {
+      source: BlobSource::Bytes(bytes.into()),
+      mime_type: normalize_type(mime_type),
+    }
+  }
+}
+
+impl Blob {
+  pub fn size(&self) -> u64 {
+    match self.source {
+      BlobSource::Bytes(ref bytes) => bytes.len() as u64,
+      #[cfg(feature = "tokio-fetch")]
+      BlobSource::Path { length, .. } => length,
+    }
+  }
+
+  pub fn get_type(&self) -> String {
+    self.mime_type.clone()
+  }
+
+  /// 截取`[start, end)`之间的数据，负数表示从末尾倒数，与JS中的`slice`相同；
+  /// 不会复制数据，新的`Blob`类型为`content_type`，未指定时为空
+  pub fn slice(&self, start: Option<i64>, end: Option<i64>, content_type: Option<&str>) -> Blob {
+    let size = self.size();
+    let relative = |index: i64| match index {
+      index if index < 0 => size.saturating_sub(index.unsigned_abs()),
+      index => (index as u64).min(size),
+    };
+    let start = start.map(relative).unwrap_or(0);
+    let end = end.map(relative).unwrap_or(size).max(start);
+    let source = match self.source {
+      BlobSource::Bytes(ref bytes) => BlobSource::Bytes(bytes.slice(start as usize..end as usize)),
+      #[cfg(feature = "tokio-fetch")]
+      BlobSource::Path { ref path, modified, offset, .. } => BlobSource::Path {
+        path: path.clone(),
+        modified,
+        offset: offset + start,
+        length: end - start,
+      },
+    };
+    Blob {
+      source,
+      mime_type: normalize_type(content_type.unwrap_or_default()),
+    }
+  }
+
+  /// 以字节流读取数据，文件在开始读取时才会被打开
+  pub fn stream(&self) -> ReadableStream<Bytes> {
+    match self.source {
+      BlobSource::Bytes(ref bytes) => ReadableStream::from_stream(futures_util::stream::iter([Ok(bytes.clone())])),
+      #[cfg(feature = "tokio-fetch")]
+      BlobSource::Path { ref path, modified, offset, length } => {
+        use futures_util::TryStreamExt;
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let path = path.clone();
+        let reader = async move {
+          let mut file = tokio::fs::File::open(&path).await?;
+          if file.metadata().await?.modified().ok() != modified {
+            let message = format!("{} was modified after the blob was created", path.display());
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+          }
+          file.seek(io::SeekFrom::Start(offset)).await?;
+          Ok(tokio_util::io::ReaderStream::new(file.take(length)))
+        };
+        ReadableStream::from_stream(futures_util::stream::once(reader).try_flatten())
+      },
+    }
+  }
+
+  /// 读取全部数据
+  pub async fn bytes(&self) -> io::Result<Bytes> {
+    let mut stream = self.stream();
+    let mut chunks = vec![];
+    while let Some(chunk) = stream.next().await {
+      chunks.push(chunk?);
+    }
+    // 内存中的数据只有一个数据块，无需复制
+    Ok(match chunks.len() {
+      1 => chunks.remove(0),
+      _ => Bytes::from(chunks.concat()),
+    })
+  }
+
+  pub async fn array_buffer(&self) -> io::Result<Vec<u8>> {
+    Ok(self.bytes().await?.to_vec())
+  }
+
+  /// 读取全部数据并按UTF-8解码，无效的字节替换为U+FFFD
+  pub async fn text(&self) -> io::Result<String> {
+    Ok(String::from_utf8_lossy(&self.bytes().await?).into_owned())
+  }
+}
+
+fn normalize_type(mime_type: &str) -> String {
+  if mime_type.bytes().all(|b| (0x20..=0x7E).contains(&b)) {
+    mime_type.to_ascii_lowercase()
+  } else {
+    String::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn slice() {
+    let blob = Blob::new("0123456789", "text/plain");
+    let read = |blob: Blob| futures_util::FutureExt::now_or_never(blob.text()).unwrap().unwrap();
+    assert_eq!(read(blob.slice(Some(2), Some(5), Some("Text/X"))), "234");
+    assert_eq!(blob.slice(None, None, Some("Text/X")).get_type(), "text/x");
+    assert_eq!(read(blob.slice(Some(-3), None, None)), "789");
+    assert_eq!(read(blob.slice(Some(8), Some(2), None)), "");
+    assert_eq!(read(blob.slice(Some(-20), Some(20), None)), "0123456789");
+  }
+
+  #[cfg(feature = "tokio-fetch")]
+  #[tokio::test]
+  async fn file_backed_blob() {
+    let path = std::env::temp_dir().join(format!("fetch-js-blob-{}.txt", std::process::id()));
+    std::fs::write(&path, "hello world").unwrap();
+    let file = File::from_path(&path).await.unwrap();
+    assert_eq!(file.name(), path.file_name().unwrap().to_str().unwrap());
+    assert_eq!(file.get_type(), "text/plain");
+    assert_eq!(file.size(), 11);
+    assert!(file.last_modified() > 0);
+    let slice = file.slice(Some(6), None, None);
+    assert_eq!(slice.text().await.unwrap(), "world");
+    assert_eq!(slice.slice(Some(1), Some(3), None).text().await.unwrap(), "or");
+
+    // 文件被修改后不能再读取
+    let handle = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+    handle.set_modified(std::time::UNIX_EPOCH).unwrap();
+    let e = file.bytes().await.unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    std::fs::remove_file(&path).unwrap();
+  }
+}
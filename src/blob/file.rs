@@ -0,0 +1,101 @@
+use std::ops::Deref;
+use std::time::{SystemTime, UNIX_EPOCH};
+use bytes::Bytes;
+use super::Blob;
+
+/// 对应JS中的`File`，在`Blob`的基础上带有文件名和修改时间
+///
+/// 通过`Deref`可以直接使用`Blob`的方法
+///
+/// # Example
+/// ```
+/// use fetch_js::blob::File;
+/// let file = File::new("a,b", "data.csv", "text/csv").with_last_modified(1_700_000_000_000);
+/// assert_eq!(file.name(), "data.csv");
+/// assert_eq!(file.last_modified(), 1_700_000_000_000);
+/// assert_eq!(file.size(), 3);
+/// assert_eq!(file.get_type(), "text/csv");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+  blob: Blob,
+  name: String,
+  last_modified: u64,
+}
+
+impl File {
+  /// 由内存中的数据创建，修改时间为当前时间
+  pub fn new(bytes: impl Into<Bytes>, name: &str, mime_type: &str) -> Self {
+    Self::from_blob(Blob::new(bytes, mime_type), name)
+  }
+
+  /// 为已有的`Blob`加上文件名，修改时间为当前时间
+  pub fn from_blob(blob: Blob, name: &str) -> Self {
+    Self {
+      blob,
+      name: name.to_string(),
+      last_modified: to_millis(SystemTime::now()),
+    }
+  }
+
+  /// 由磁盘上的文件创建，文件名、类型和修改时间取自路径和元数据，内容在读取时才会被读取
+  ///
+  /// 创建后文件被修改时，读取会返回错误
+  #[cfg(feature = "tokio-fetch")]
+  pub async fn from_path(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+    let path = path.as_ref();
+    let metadata = tokio::fs::metadata(path).await?;
+    if !metadata.is_file() {
+      let message = format!("{} is not a file", path.display());
+      return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, message));
+    }
+    let modified = metadata.modified().ok();
+    let blob = Blob {
+      source: super::BlobSource::Path {
+        path: path.into(),
+        modified,
+        offset: 0,
+        length: metadata.len(),
+      },
+      mime_type: crate::mime::from_path(path).unwrap_or_default().to_string(),
+    };
+    Ok(Self {
+      blob,
+      name: path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+      last_modified: modified.map(to_millis).unwrap_or_default(),
+    })
+  }
+
+  /// 设置修改时间，单位为自UNIX纪元起的毫秒数
+  pub fn with_last_modified(mut self, last_modified: u64) -> Self {
+    self.last_modified = last_modified;
+    self
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// 修改时间，单位为自UNIX纪元起的毫秒数
+  pub fn last_modified(&self) -> u64 {
+    self.last_modified
+  }
+}
+
+impl Deref for File {
+  type Target = Blob;
+
+  fn deref(&self) -> &Blob {
+    &self.blob
+  }
+}
+
+impl From<File> for Blob {
+  fn from(file: File) -> Self {
+    file.blob
+  }
+}
+
+fn to_millis(time: SystemTime) -> u64 {
+  time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+}
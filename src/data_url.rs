@@ -0,0 +1,58 @@
+use bytes::Bytes;
+use crate::url::{percent_decode, URL};
+use crate::{base64, mime};
+
+/// 解析后的`data:` URL
+#[derive(Debug, PartialEq)]
+pub(crate) struct DataUrl {
+  pub(crate) mime_type: String,
+  pub(crate) body: Bytes,
+}
+
+/// 按照Fetch规范的data: URL处理流程解析，格式错误时返回`None`
+///
+/// 逗号前是MIME类型，以`;base64`结尾时正文按forgiving-base64解码，
+/// 正文中的`%XX`转义会先被解码；MIME类型无效时使用`text/plain;charset=US-ASCII`
+pub(crate) fn parse(url: &URL) -> Option<DataUrl> {
+  let href = url.get_href_without_hash();
+  let input = href.strip_prefix(url.get_protocol().as_str())?;
+  let (mime_type, body) = input.split_once(',')?;
+  let mut mime_type = mime_type.trim_matches(|c: char| c.is_ascii_whitespace());
+  let mut body = percent_decode(body);
+  if let Some((rest, encoding)) = mime_type.rsplit_once(';') {
+    if encoding.trim_start_matches(' ').eq_ignore_ascii_case("base64") {
+      body = base64::decode(&body)?;
+      mime_type = rest.trim_end_matches(' ');
+    }
+  }
+  let mime_type = match mime_type {
+    mime_type if mime_type.starts_with(';') => mime::parse(&format!("text/plain{}", mime_type)),
+    mime_type => mime::parse(mime_type),
+  };
+  Some(DataUrl {
+    mime_type: mime_type.unwrap_or_else(|| "text/plain;charset=US-ASCII".to_string()),
+    body: Bytes::from(body),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn data(url: &str) -> Option<(String, Vec<u8>)> {
+    parse(&URL::new(url)).map(|data| (data.mime_type, data.body.to_vec()))
+  }
+
+  #[test]
+  fn process() {
+    assert_eq!(data("data:text/plain;base64,SGVsbG8="), Some(("text/plain".to_string(), b"Hello".to_vec())));
+    assert_eq!(data("data:,a%20b#frag"), Some(("text/plain;charset=US-ASCII".to_string(), b"a b".to_vec())));
+    assert_eq!(data("data:;charset=UTF-8,x"), Some(("text/plain;charset=UTF-8".to_string(), b"x".to_vec())));
+    assert_eq!(data("data:text/html ; base64 ,PGI%2B"), Some(("text/html".to_string(), b"<b>".to_vec())));
+    assert_eq!(data("data:image/png;base64,Zm9v YmE"), Some(("image/png".to_string(), b"fooba".to_vec())));
+    assert_eq!(data("data:text/plain,a?b=1"), Some(("text/plain".to_string(), b"a?b=1".to_vec())));
+    assert_eq!(data("data:base64,QQ=="), Some(("text/plain;charset=US-ASCII".to_string(), b"QQ==".to_vec())));
+    assert_eq!(data("data:;base64,QQ="), None);
+    assert_eq!(data("data:text/plain"), None);
+  }
+}
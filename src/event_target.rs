@@ -0,0 +1,348 @@
+//! 与DOM相同的`EventTarget`和`Event`
+//!
+//! 目前只有`AbortSignal`通过它分发`abort`事件；`ReadableStream`、`Response`和请求正文不会分发事件
+use std::any::Any;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::SystemTime;
+use crate::abort_controller::AbortSignal;
+
+type ListenerFn = Arc<dyn Fn(&mut Event) + Send + Sync>;
+
+/// 事件对象，由`EventTarget::dispatch_event`分发给监听器
+pub struct Event {
+  event_type: String,
+  time_stamp: SystemTime,
+  cancelable: bool,
+  default_prevented: bool,
+  detail: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl Event {
+  /// 创建一个指定类型的事件
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::event_target::Event;
+  /// let event = Event::new("abort");
+  /// assert_eq!(event.get_type(), "abort");
+  /// assert_eq!(event.default_prevented(), false);
+  /// ```
+  pub fn new(event_type: &str) -> Self {
+    Self {
+      event_type: event_type.to_string(),
+      time_stamp: SystemTime::now(),
+      cancelable: false,
+      default_prevented: false,
+      detail: None,
+    }
+  }
+
+  /// 设置事件是否可以被`prevent_default`取消
+  pub fn with_cancelable(mut self, cancelable: bool) -> Self {
+    self.cancelable = cancelable;
+    self
+  }
+
+  /// 为事件附加任意数据，监听器可以通过`get_detail`取回
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::event_target::Event;
+  /// let event = Event::new("progress").with_detail(42u64);
+  /// assert_eq!(event.get_detail::<u64>(), Some(&42));
+  /// assert_eq!(event.get_detail::<String>(), None);
+  /// ```
+  pub fn with_detail<T: Any + Send + Sync>(mut self, detail: T) -> Self {
+    self.detail = Some(Box::new(detail));
+    self
+  }
+}
+
+impl Event {
+  pub fn get_type(&self) -> String {
+    self.event_type.clone()
+  }
+
+  pub fn get_time_stamp(&self) -> SystemTime {
+    self.time_stamp
+  }
+
+  pub fn get_cancelable(&self) -> bool {
+    self.cancelable
+  }
+
+  pub fn get_detail<T: Any>(&self) -> Option<&T> {
+    self.detail.as_ref().and_then(|detail| detail.downcast_ref::<T>())
+  }
+
+  pub fn default_prevented(&self) -> bool {
+    self.default_prevented
+  }
+
+  /// 取消事件的默认行为，仅对可取消的事件生效
+  pub fn prevent_default(&mut self) {
+    if self.cancelable {
+      self.default_prevented = true;
+    }
+  }
+}
+
+impl std::fmt::Debug for Event {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Event")
+      .field("event_type", &self.event_type)
+      .field("time_stamp", &self.time_stamp)
+      .field("cancelable", &self.cancelable)
+      .field("default_prevented", &self.default_prevented)
+      .finish()
+  }
+}
+
+/// `add_event_listener`的选项
+#[derive(Default)]
+pub struct AddEventListenerOptions {
+  /// 监听器在第一次触发后自动移除
+  pub once: bool,
+  /// 该信号中止时自动移除监听器
+  pub signal: Option<AbortSignal>,
+}
+
+struct Registration {
+  id: usize,
+  event_type: String,
+  listener: ListenerFn,
+  once: bool,
+  /// 在`signal`选项上注册的监听器，本监听器被移除时一并移除
+  abort: Option<(Weak<Mutex<Listeners>>, usize)>,
+}
+
+impl Registration {
+  fn detach(self) {
+    if let Some((listeners, id)) = self.abort {
+      if let Some(listeners) = listeners.upgrade() {
+        EventTarget { listeners }.remove_event_listener("abort", id);
+      }
+    }
+  }
+}
+
+#[derive(Default)]
+struct Listeners {
+  next_id: usize,
+  registrations: Vec<Registration>,
+}
+
+/// 事件目标，可以注册监听器并分发事件
+///
+/// `EventTarget`内部共享状态，克隆后的实例指向同一组监听器
+#[derive(Clone, Default)]
+pub struct EventTarget {
+  listeners: Arc<Mutex<Listeners>>,
+}
+
+impl EventTarget {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// 注册事件监听器，返回可用于`remove_event_listener`的监听器id；
+  /// `signal`选项已经中止时不会注册，返回`None`
+  ///
+  /// # Example
+  /// ```
+  /// use std::sync::Arc;
+  /// use std::sync::atomic::{AtomicUsize, Ordering};
+  /// use fetch_js::event_target::{AddEventListenerOptions, Event, EventTarget};
+  ///
+  /// let target = EventTarget::new();
+  /// let count = Arc::new(AtomicUsize::new(0));
+  /// let counter = count.clone();
+  /// target.add_event_listener("ping", move |_| {
+  ///   counter.fetch_add(1, Ordering::SeqCst);
+  /// }, AddEventListenerOptions { once: true, ..Default::default() });
+  /// target.dispatch_event(&mut Event::new("ping"));
+  /// target.dispatch_event(&mut Event::new("ping"));
+  /// assert_eq!(count.load(Ordering::SeqCst), 1);
+  /// ```
+  pub fn add_event_listener<F>(&self, event_type: &str, listener: F, options: AddEventListenerOptions) -> Option<usize>
+  where
+    F: Fn(&mut Event) + Send + Sync + 'static,
+  {
+    self.add_listener(event_type, Arc::new(listener), options)
+  }
+
+  fn add_listener(&self, event_type: &str, listener: ListenerFn, options: AddEventListenerOptions) -> Option<usize> {
+    if options.signal.as_ref().is_some_and(AbortSignal::aborted) {
+      return None;
+    }
+    let id = {
+      let mut listeners = self.listeners.lock().unwrap();
+      let id = listeners.next_id;
+      listeners.next_id += 1;
+      listeners.registrations.push(Registration {
+        id,
+        event_type: event_type.to_string(),
+        listener,
+        once: options.once,
+        abort: None,
+      });
+      id
+    };
+    if let Some(signal) = options.signal {
+      let weak: Weak<Mutex<Listeners>> = Arc::downgrade(&self.listeners);
+      let removed_type = event_type.to_string();
+      let abort = signal.add_listener("abort", Arc::new(move |_| {
+        if let Some(listeners) = weak.upgrade() {
+          EventTarget { listeners }.remove_event_listener(&removed_type, id);
+        }
+      }), AddEventListenerOptions { once: true, signal: None })?;
+      let mut listeners = self.listeners.lock().unwrap();
+      match listeners.registrations.iter_mut().find(|r| r.id == id) {
+        Some(registration) => registration.abort = Some((Arc::downgrade(&signal.listeners), abort)),
+        None => {
+          drop(listeners);
+          signal.remove_event_listener("abort", abort);
+        },
+      }
+      // 注册期间信号可能已经中止
+      if signal.aborted() {
+        self.remove_event_listener(event_type, id);
+        return None;
+      }
+    }
+    Some(id)
+  }
+
+  /// 移除事件监听器
+  pub fn remove_event_listener(&self, event_type: &str, id: usize) {
+    let removed = {
+      let mut listeners = self.listeners.lock().unwrap();
+      listeners.registrations.iter()
+        .position(|r| r.id == id && r.event_type == event_type)
+        .map(|index| listeners.registrations.remove(index))
+    };
+    // 释放锁之后再移除信号上的监听器，信号可能就是这个目标
+    if let Some(registration) = removed {
+      registration.detach();
+    }
+  }
+
+  /// 向所有匹配类型的监听器分发事件，若事件被`prevent_default`取消则返回`false`
+  ///
+  /// 监听器在调用前被复制出来，因此可以在监听器中安全地增删监听器
+  pub fn dispatch_event(&self, event: &mut Event) -> bool {
+    let (matched, once) = {
+      let mut listeners = self.listeners.lock().unwrap();
+      let matched = listeners.registrations.iter()
+        .filter(|r| r.event_type == event.event_type)
+        .map(|r| r.listener.clone())
+        .collect::<Vec<ListenerFn>>();
+      let (once, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut listeners.registrations).into_iter()
+        .partition(|r| r.once && r.event_type == event.event_type);
+      listeners.registrations = rest;
+      (matched, once)
+    };
+    once.into_iter().for_each(Registration::detach);
+    for listener in matched {
+      listener(event);
+    }
+    !event.default_prevented
+  }
+
+  /// 返回某一类型已注册的监听器数量
+  pub fn listener_count(&self, event_type: &str) -> usize {
+    let listeners = self.listeners.lock().unwrap();
+    listeners.registrations.iter().filter(|r| r.event_type == event_type).count()
+  }
+}
+
+impl std::fmt::Debug for EventTarget {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let listeners = self.listeners.lock().unwrap();
+    f.debug_struct("EventTarget")
+      .field("listeners", &listeners.registrations.len())
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use crate::abort_controller::AbortController;
+
+  #[test]
+  fn dispatch_only_matching_type() {
+    let target = EventTarget::new();
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+    target.add_event_listener("a", move |_| {
+      counter.fetch_add(1, Ordering::SeqCst);
+    }, AddEventListenerOptions::default());
+    target.dispatch_event(&mut Event::new("b"));
+    target.dispatch_event(&mut Event::new("a"));
+    target.dispatch_event(&mut Event::new("a"));
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+  }
+
+  #[test]
+  fn remove_listener() {
+    let target = EventTarget::new();
+    let id = target.add_event_listener("a", |_| panic!("removed"), AddEventListenerOptions::default()).unwrap();
+    target.remove_event_listener("a", id);
+    assert_eq!(target.listener_count("a"), 0);
+    target.dispatch_event(&mut Event::new("a"));
+  }
+
+  #[test]
+  fn prevent_default() {
+    let target = EventTarget::new();
+    target.add_event_listener("a", |e| e.prevent_default(), AddEventListenerOptions::default());
+    assert!(target.dispatch_event(&mut Event::new("a")));
+    assert!(!target.dispatch_event(&mut Event::new("a").with_cancelable(true)));
+  }
+
+  #[test]
+  fn signal_removes_listener() {
+    let target = EventTarget::new();
+    let controller = AbortController::new();
+    target.add_event_listener("a", |_| {}, AddEventListenerOptions {
+      once: false,
+      signal: Some(controller.signal.clone()),
+    });
+    assert_eq!(target.listener_count("a"), 1);
+    controller.abort(None);
+    assert_eq!(target.listener_count("a"), 0);
+    assert!(target.add_event_listener("a", |_| {}, AddEventListenerOptions {
+      once: false,
+      signal: Some(controller.signal.clone()),
+    }).is_none());
+    assert_eq!(target.listener_count("a"), 0);
+  }
+
+  #[test]
+  fn signal_listener_removed_with_listener() {
+    let target = EventTarget::new();
+    let controller = AbortController::new();
+    let options = |once| AddEventListenerOptions { once, signal: Some(controller.signal.clone()) };
+    let id = target.add_event_listener("a", |_| {}, options(false)).unwrap();
+    assert_eq!(controller.signal.listener_count("abort"), 1);
+    target.remove_event_listener("a", id);
+    assert_eq!(controller.signal.listener_count("abort"), 0);
+
+    target.add_event_listener("a", |_| {}, options(true));
+    target.dispatch_event(&mut Event::new("a"));
+    assert_eq!(controller.signal.listener_count("abort"), 0);
+  }
+
+  #[test]
+  fn listener_can_mutate_target() {
+    let target = EventTarget::new();
+    let inner = target.clone();
+    target.add_event_listener("a", move |_| {
+      inner.add_event_listener("a", |_| {}, AddEventListenerOptions::default());
+    }, AddEventListenerOptions { once: true, signal: None });
+    target.dispatch_event(&mut Event::new("a"));
+    assert_eq!(target.listener_count("a"), 1);
+  }
+}
@@ -0,0 +1,194 @@
+mod multipart;
+
+pub use multipart::MultipartLimits;
+pub(crate) use multipart::{boundary, encode, encoded_length};
+
+use std::io;
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use thiserror::Error;
+use crate::blob::{Blob, File};
+use crate::streams::ReadableStream;
+use crate::url::URLSearchParams;
+
+/// 解析表单正文失败时返回的错误
+#[derive(Debug, Error)]
+pub enum FormDataError {
+  #[error("unsupported content type for form data: {0}")]
+  UnsupportedContentType(String),
+  #[error("malformed multipart body: {0}")]
+  Malformed(String),
+  #[error("multipart body has more than {0} parts")]
+  TooManyParts(usize),
+  #[error("multipart part is larger than {0} bytes")]
+  PartTooLarge(usize),
+  #[error("failed to read body: {0}")]
+  Io(#[from] io::Error),
+}
+
+/// 按`Content-Type`解析`multipart/form-data`或`application/x-www-form-urlencoded`正文
+pub(crate) async fn parse(
+  mut body: ReadableStream<Bytes>,
+  content_type: Option<&str>,
+  limits: MultipartLimits,
+) -> Result<FormData, FormDataError> {
+  let content_type = content_type.unwrap_or_default();
+  let essence = content_type.split(';').next().unwrap_or_default().trim();
+  if essence.eq_ignore_ascii_case("multipart/form-data") {
+    let boundary = multipart::boundary_of(content_type)
+      .ok_or_else(|| FormDataError::Malformed("missing boundary".to_string()))?;
+    return multipart::parse(body, &boundary, limits).await;
+  }
+  if !essence.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+    return Err(FormDataError::UnsupportedContentType(content_type.to_string()));
+  }
+  let mut bytes = BytesMut::new();
+  while let Some(chunk) = body.next().await {
+    bytes.extend_from_slice(&chunk?);
+  }
+  let mut form = FormData::new();
+  for (name, value) in URLSearchParams::parse(&String::from_utf8_lossy(&bytes)) {
+    form.append(&name, value);
+  }
+  Ok(form)
+}
+
+/// 对应JS中的`FormData`，按添加顺序保存表单字段，同名字段可以有多个
+///
+/// 作为请求正文时以`multipart/form-data`格式发送，文件字段在发送时才会被读取
+///
+/// # Example
+/// ```
+/// use fetch_js::blob::{Blob, File};
+/// use fetch_js::form_data::FormData;
+///
+/// let mut form = FormData::new();
+/// form.append("tag", "a");
+/// form.append("tag", "b");
+/// form.append("avatar", File::new(vec![0x89, 0x50], "me.png", "image/png"));
+/// form.append("raw", Blob::new("...", ""));
+/// assert_eq!(form.get("tag").unwrap().as_text(), Some("a"));
+/// assert_eq!(form.get_all("tag").len(), 2);
+/// assert_eq!(form.get("avatar").unwrap().as_file().unwrap().name(), "me.png");
+/// assert_eq!(form.get("raw").unwrap().as_file().unwrap().name(), "blob");
+///
+/// form.set("tag", "c");
+/// assert_eq!(form.get_all("tag").len(), 1);
+/// form.delete("avatar");
+/// assert!(!form.has("avatar"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FormData {
+  entries: Vec<(String, FormDataValue)>,
+}
+
+impl FormData {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// 在末尾添加字段，不影响已有的同名字段
+  pub fn append(&mut self, name: &str, value: impl Into<FormDataValue>) {
+    self.entries.push((name.to_string(), value.into()));
+  }
+
+  /// 替换第一个同名字段并删除其余的同名字段，不存在时添加到末尾
+  pub fn set(&mut self, name: &str, value: impl Into<FormDataValue>) {
+    let mut value = Some(value.into());
+    self.entries.retain_mut(|(key, entry)| {
+      if key != name {
+        return true;
+      }
+      match value.take() {
+        Some(value) => {
+          *entry = value;
+          true
+        },
+        None => false,
+      }
+    });
+    if let Some(value) = value {
+      self.entries.push((name.to_string(), value));
+    }
+  }
+
+  /// 获取第一个同名字段的值
+  pub fn get(&self, name: &str) -> Option<&FormDataValue> {
+    self.entries.iter().find(|(key, _)| key == name).map(|(_, value)| value)
+  }
+
+  /// 获取所有同名字段的值
+  pub fn get_all(&self, name: &str) -> Vec<&FormDataValue> {
+    self.entries.iter().filter(|(key, _)| key == name).map(|(_, value)| value).collect()
+  }
+
+  /// 删除所有同名字段
+  pub fn delete(&mut self, name: &str) {
+    self.entries.retain(|(key, _)| key != name);
+  }
+
+  pub fn has(&self, name: &str) -> bool {
+    self.entries.iter().any(|(key, _)| key == name)
+  }
+
+  /// 按添加顺序遍历所有字段
+  pub fn entries(&self) -> impl Iterator<Item = (&str, &FormDataValue)> {
+    self.entries.iter().map(|(key, value)| (key.as_str(), value))
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+}
+
+/// 表单字段的值，可以是文本或者文件
+#[derive(Debug, Clone)]
+pub enum FormDataValue {
+  Text(String),
+  File(File),
+}
+
+impl FormDataValue {
+  pub fn as_text(&self) -> Option<&str> {
+    match self {
+      FormDataValue::Text(text) => Some(text),
+      FormDataValue::File(_) => None,
+    }
+  }
+
+  pub fn as_file(&self) -> Option<&File> {
+    match self {
+      FormDataValue::Text(_) => None,
+      FormDataValue::File(file) => Some(file),
+    }
+  }
+}
+
+impl From<&str> for FormDataValue {
+  fn from(text: &str) -> Self {
+    FormDataValue::Text(text.to_string())
+  }
+}
+
+impl From<String> for FormDataValue {
+  fn from(text: String) -> Self {
+    FormDataValue::Text(text)
+  }
+}
+
+impl From<File> for FormDataValue {
+  fn from(file: File) -> Self {
+    FormDataValue::File(file)
+  }
+}
+
+/// 与JS相同，没有文件名的`Blob`以`blob`为文件名
+impl From<Blob> for FormDataValue {
+  fn from(blob: Blob) -> Self {
+    FormDataValue::File(File::from_blob(blob, "blob"))
+  }
+}
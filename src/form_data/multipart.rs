@@ -0,0 +1,336 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::StreamExt;
+use crate::streams::ReadableStream;
+use crate::blob::{Blob, File};
+use super::{FormData, FormDataError, FormDataValue};
+
+/// 生成不易与正文冲突的分隔符；它不是密码学安全的随机值，可能被猜到
+pub(crate) fn boundary() -> String {
+  let random = |salt: u64| {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(salt);
+    hasher.finish()
+  };
+  let nanos = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_nanos() as u64)
+    .unwrap_or_default();
+  format!("----FetchJsFormBoundary{:016x}{:016x}", random(nanos), random(!nanos))
+}
+
+/// 编码后的一段数据，文件内容在发送时才会被读取
+enum Segment {
+  Bytes(Bytes),
+  Blob(Blob),
+}
+
+/// 按HTML规范转义字段名和文件名中的换行和引号
+fn escape(name: &str) -> String {
+  name.replace('\n', "%0A").replace('\r', "%0D").replace('"', "%22")
+}
+
+/// 文本值中的换行统一为CRLF
+fn normalize_newlines(text: &str) -> String {
+  text.replace("\r\n", "\n").replace('\r', "\n").replace('\n', "\r\n")
+}
+
+fn segments(form: &FormData, boundary: &str) -> Vec<Segment> {
+  let mut segments = vec![];
+  for (name, value) in form.entries() {
+    let mut head = format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"", boundary, escape(name));
+    match value {
+      FormDataValue::Text(text) => {
+        head.push_str("\r\n\r\n");
+        head.push_str(&normalize_newlines(text));
+        head.push_str("\r\n");
+        segments.push(Segment::Bytes(Bytes::from(head)));
+      },
+      FormDataValue::File(file) => {
+        let content_type = match file.get_type() {
+          content_type if content_type.is_empty() => "application/octet-stream".to_string(),
+          content_type => content_type,
+        };
+        head.push_str(&format!("; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n", escape(file.name()), content_type));
+        segments.push(Segment::Bytes(Bytes::from(head)));
+        segments.push(Segment::Blob(Blob::from(file.clone())));
+        segments.push(Segment::Bytes(Bytes::from_static(b"\r\n")));
+      },
+    }
+  }
+  segments.push(Segment::Bytes(Bytes::from(format!("--{}--\r\n", boundary))));
+  segments
+}
+
+/// `multipart/form-data`编码后的总长度
+pub(crate) fn encoded_length(form: &FormData, boundary: &str) -> u64 {
+  segments(form, boundary).iter().map(|segment| match segment {
+    Segment::Bytes(bytes) => bytes.len() as u64,
+    Segment::Blob(blob) => blob.size(),
+  }).sum()
+}
+
+/// 以`multipart/form-data`格式编码为字节流
+pub(crate) fn encode(form: &FormData, boundary: &str) -> ReadableStream<Bytes> {
+  let stream = futures_util::stream::iter(segments(form, boundary)).flat_map(|segment| match segment {
+    Segment::Bytes(bytes) => ReadableStream::from_stream(futures_util::stream::iter([Ok(bytes)])),
+    Segment::Blob(blob) => blob.stream(),
+  });
+  ReadableStream::from_stream(stream)
+}
+
+/// 解析`multipart/form-data`正文时的限制
+#[derive(Debug, Clone, Copy)]
+pub struct MultipartLimits {
+  /// 最多允许的字段数
+  pub max_parts: usize,
+  /// 单个字段内容的最大字节数
+  pub max_part_size: usize,
+  /// 单个字段头部的最大字节数
+  pub max_header_size: usize,
+}
+
+impl Default for MultipartLimits {
+  fn default() -> Self {
+    Self {
+      max_parts: 1000,
+      max_part_size: 16 * 1024 * 1024,
+      max_header_size: 16 * 1024,
+    }
+  }
+}
+
+/// 从`Content-Type`中取出`boundary`参数
+pub(crate) fn boundary_of(content_type: &str) -> Option<String> {
+  parameters(content_type.split_once(';')?.1)
+    .into_iter()
+    .find(|(key, _)| key.eq_ignore_ascii_case("boundary"))
+    .map(|(_, value)| value)
+    .filter(|boundary| !boundary.is_empty() && boundary.len() <= 70)
+}
+
+/// 解析`; key=value; key="quoted value"`形式的参数
+fn parameters(input: &str) -> Vec<(String, String)> {
+  let mut params = vec![];
+  let mut rest = input;
+  loop {
+    rest = rest.trim_start_matches([';', ' ', '\t']);
+    let Some((key, value)) = rest.split_once('=') else {
+      return params;
+    };
+    let key = key.trim().to_string();
+    let value = value.trim_start();
+    if let Some(quoted) = value.strip_prefix('"') {
+      let mut unquoted = String::new();
+      let mut chars = quoted.char_indices();
+      rest = "";
+      while let Some((i, c)) = chars.next() {
+        match c {
+          '\\' => unquoted.extend(chars.next().map(|(_, c)| c)),
+          '"' => {
+            rest = &quoted[i + 1..];
+            break;
+          },
+          c => unquoted.push(c),
+        }
+      }
+      params.push((key, unquoted));
+    } else {
+      let end = value.find(';').unwrap_or(value.len());
+      params.push((key, value[..end].trim().to_string()));
+      rest = &value[end..];
+    }
+  }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+  haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+struct Part {
+  name: String,
+  filename: Option<String>,
+  content_type: String,
+  data: BytesMut,
+}
+
+fn parse_part_headers(block: &[u8]) -> Result<Part, FormDataError> {
+  let block = String::from_utf8_lossy(block);
+  let mut disposition = None;
+  let mut content_type = None;
+  for line in block.split("\r\n") {
+    let (name, value) = line.split_once(':')
+      .ok_or_else(|| FormDataError::Malformed(format!("invalid part header: {}", line)))?;
+    if name.trim().eq_ignore_ascii_case("content-disposition") {
+      disposition = Some(value.trim().to_string());
+    } else if name.trim().eq_ignore_ascii_case("content-type") {
+      content_type = Some(value.trim().to_string());
+    }
+  }
+  let disposition = disposition
+    .ok_or_else(|| FormDataError::Malformed("part is missing Content-Disposition".to_string()))?;
+  let (kind, params) = disposition.split_once(';').unwrap_or((&disposition, ""));
+  if !kind.trim().eq_ignore_ascii_case("form-data") {
+    return Err(FormDataError::Malformed(format!("unexpected Content-Disposition: {}", disposition)));
+  }
+  let params = parameters(params);
+  let param = |key: &str| params.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v.clone());
+  Ok(Part {
+    name: param("name").ok_or_else(|| FormDataError::Malformed("part is missing a name".to_string()))?,
+    filename: param("filename"),
+    content_type: content_type.unwrap_or_else(|| "text/plain".to_string()),
+    data: BytesMut::new(),
+  })
+}
+
+enum State {
+  Preamble,
+  Delimiter,
+  Headers,
+  Body(Part),
+}
+
+/// 边读取边解析`multipart/form-data`正文，超出`limits`时立即返回错误
+pub(crate) async fn parse(
+  mut stream: ReadableStream<Bytes>,
+  boundary: &str,
+  limits: MultipartLimits,
+) -> Result<FormData, FormDataError> {
+  let delimiter = format!("\r\n--{}", boundary).into_bytes();
+  // 第一个分隔符前没有CRLF，补上后与其他分隔符统一处理
+  let mut buffer = BytesMut::from(&b"\r\n"[..]);
+  let mut form = FormData::new();
+  let mut state = State::Preamble;
+  let mut eof = false;
+  loop {
+    match state {
+      State::Preamble => match find(&buffer, &delimiter) {
+        Some(position) => {
+          buffer.advance(position + delimiter.len());
+          state = State::Delimiter;
+          continue;
+        },
+        None => buffer.advance(buffer.len().saturating_sub(delimiter.len() - 1)),
+      },
+      State::Delimiter => {
+        if buffer.starts_with(b"--") {
+          return Ok(form);
+        }
+        if let Some(position) = find(&buffer, b"\r\n") {
+          if buffer[..position].iter().any(|b| *b != b' ' && *b != b'\t') {
+            return Err(FormDataError::Malformed("unexpected data after boundary".to_string()));
+          }
+          buffer.advance(position + 2);
+          state = State::Headers;
+          continue;
+        }
+      },
+      State::Headers => {
+        if let Some(position) = find(&buffer, b"\r\n\r\n") {
+          if form.len() >= limits.max_parts {
+            return Err(FormDataError::TooManyParts(limits.max_parts));
+          }
+          let part = parse_part_headers(&buffer[..position])?;
+          buffer.advance(position + 4);
+          state = State::Body(part);
+          continue;
+        }
+        if buffer.len() > limits.max_header_size {
+          return Err(FormDataError::Malformed("part headers are too large".to_string()));
+        }
+      },
+      State::Body(ref mut part) => {
+        // 保留可能是分隔符开头的部分，其余的数据移入字段内容
+        let (length, found) = match find(&buffer, &delimiter) {
+          Some(position) => (position, true),
+          None => (buffer.len().saturating_sub(delimiter.len() - 1), false),
+        };
+        if part.data.len() + length > limits.max_part_size {
+          return Err(FormDataError::PartTooLarge(limits.max_part_size));
+        }
+        part.data.extend_from_slice(&buffer[..length]);
+        buffer.advance(length);
+        if found {
+          buffer.advance(delimiter.len());
+          let State::Body(part) = std::mem::replace(&mut state, State::Delimiter) else {
+            unreachable!()
+          };
+          let value = match part.filename {
+            Some(filename) => FormDataValue::File(File::new(part.data.freeze(), &filename, &part.content_type)),
+            None => FormDataValue::Text(String::from_utf8_lossy(&part.data).into_owned()),
+          };
+          form.append(&part.name, value);
+          continue;
+        }
+      },
+    }
+    if eof {
+      return Err(FormDataError::Malformed("unexpected end of multipart body".to_string()));
+    }
+    match stream.next().await {
+      Some(chunk) => buffer.extend_from_slice(&chunk?),
+      None => eof = true,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use futures_util::FutureExt;
+  use super::*;
+
+  #[test]
+  fn encode_form() {
+    let mut form = FormData::new();
+    form.append("note", "line1\nline2");
+    form.append("say \"hi\"", "ok");
+    form.append("file", File::new("abc", "a.txt", ""));
+    let expected = concat!(
+      "--b\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nline1\r\nline2\r\n",
+      "--b\r\nContent-Disposition: form-data; name=\"say %22hi%22\"\r\n\r\nok\r\n",
+      "--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n",
+      "Content-Type: application/octet-stream\r\n\r\nabc\r\n",
+      "--b--\r\n",
+    );
+    let chunks: Vec<_> = encode(&form, "b").map(Result::unwrap).collect().now_or_never().unwrap();
+    assert_eq!(chunks.concat(), expected.as_bytes());
+    assert_eq!(encoded_length(&form, "b"), expected.len() as u64);
+    assert_ne!(boundary(), boundary());
+  }
+
+  fn parse_chunks(chunks: Vec<&'static str>, limits: MultipartLimits) -> Result<FormData, FormDataError> {
+    let stream = futures_util::stream::iter(chunks.into_iter().map(|c| Ok(Bytes::from(c))));
+    parse(ReadableStream::from_stream(stream), "b", limits).now_or_never().unwrap()
+  }
+
+  #[test]
+  fn parse_form() {
+    // 分隔符被切分到不同的数据块中
+    let chunks = vec![
+      "preamble\r\n--b\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n-",
+      "-b\r\nContent-Disposition: form-data; name=\"f\"; filename=\"x \\\"y\\\".txt\"\r\n",
+      "Content-Type: text/csv\r\n\r\nc,s\r\nv\r\n",
+      // 与浏览器一致，参数值中的%22等原样保留
+      "--b\r\nContent-Disposition: form-data; name=\"g\"; filename=\"100%22off.csv\"\r\n\r\n\r\n--b--\r\nepilogue",
+    ];
+    let form = parse_chunks(chunks, MultipartLimits::default()).unwrap();
+    assert_eq!(form.get("a").unwrap().as_text(), Some("1"));
+    let file = form.get("f").unwrap().as_file().unwrap();
+    assert_eq!(file.name(), "x \"y\".txt");
+    assert_eq!(file.get_type(), "text/csv");
+    assert_eq!(file.bytes().now_or_never().unwrap().unwrap(), "c,s\r\nv");
+    assert_eq!(form.get("g").unwrap().as_file().unwrap().name(), "100%22off.csv");
+    assert_eq!(boundary_of("multipart/form-data; boundary=\"b\""), Some("b".to_string()));
+  }
+
+  #[test]
+  fn parse_limits() {
+    let part = "--b\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n12345\r\n";
+    let limits = MultipartLimits { max_parts: 1, ..Default::default() };
+    assert!(matches!(parse_chunks(vec![part, part, "--b--"], limits), Err(FormDataError::TooManyParts(1))));
+    let limits = MultipartLimits { max_part_size: 4, ..Default::default() };
+    assert!(matches!(parse_chunks(vec![part, "--b--"], limits), Err(FormDataError::PartTooLarge(4))));
+    assert!(matches!(parse_chunks(vec![part], MultipartLimits::default()), Err(FormDataError::Malformed(_))));
+  }
+}
@@ -1,6 +1,19 @@
 pub mod url;
+pub mod blob;
+pub mod request;
 pub mod request_init;
 pub mod abort_controller;
+pub mod event_target;
+pub mod streams;
+pub mod form_data;
 mod realization;
 #[cfg(feature = "tokio-fetch")]
+mod base64;
+#[cfg(feature = "tokio-fetch")]
+mod text_decoder;
+#[cfg(feature = "tokio-fetch")]
+mod mime;
+#[cfg(feature = "tokio-fetch")]
+mod data_url;
+#[cfg(feature = "tokio-fetch")]
 pub use realization::tokio::*;
\ No newline at end of file
@@ -0,0 +1,130 @@
+use std::path::Path;
+use crate::request_init::is_token;
+
+/// 常见扩展名对应的MIME类型
+const MIME_TYPES: [(&str, &str); 32] = [
+  ("html", "text/html"),
+  ("htm", "text/html"),
+  ("css", "text/css"),
+  ("js", "text/javascript"),
+  ("mjs", "text/javascript"),
+  ("txt", "text/plain"),
+  ("csv", "text/csv"),
+  ("md", "text/markdown"),
+  ("xml", "application/xml"),
+  ("json", "application/json"),
+  ("pdf", "application/pdf"),
+  ("zip", "application/zip"),
+  ("gz", "application/gzip"),
+  ("tar", "application/x-tar"),
+  ("wasm", "application/wasm"),
+  ("bin", "application/octet-stream"),
+  ("png", "image/png"),
+  ("jpg", "image/jpeg"),
+  ("jpeg", "image/jpeg"),
+  ("gif", "image/gif"),
+  ("webp", "image/webp"),
+  ("svg", "image/svg+xml"),
+  ("ico", "image/x-icon"),
+  ("avif", "image/avif"),
+  ("mp3", "audio/mpeg"),
+  ("wav", "audio/wav"),
+  ("ogg", "audio/ogg"),
+  ("mp4", "video/mp4"),
+  ("webm", "video/webm"),
+  ("woff", "font/woff"),
+  ("woff2", "font/woff2"),
+  ("ttf", "font/ttf"),
+];
+
+/// 根据扩展名猜测文件的MIME类型，未知的扩展名返回`None`
+pub(crate) fn from_path(path: &Path) -> Option<&'static str> {
+  let extension = path.extension()?.to_str()?;
+  MIME_TYPES.iter()
+    .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+    .map(|(_, mime)| *mime)
+}
+
+/// 按照MIME Sniffing规范解析MIME类型并序列化，类型和参数名转为小写，
+/// 无效的参数被忽略，重复的参数只保留第一个，格式错误时返回`None`
+pub(crate) fn parse(input: &str) -> Option<String> {
+  let input = input.trim_matches([' ', '\t', '\r', '\n']);
+  let (essence, mut rest) = input.split_once(';').map_or((input, None), |(essence, rest)| (essence, Some(rest)));
+  let (kind, subtype) = essence.split_once('/')?;
+  let subtype = subtype.trim_end_matches([' ', '\t', '\r', '\n']);
+  if !is_token(kind) || !is_token(subtype) {
+    return None;
+  }
+  let mut params: Vec<(String, String)> = vec![];
+  while let Some(input) = rest {
+    let input = input.trim_start_matches([' ', '\t', '\r', '\n']);
+    let end = input.find([';', '=']).unwrap_or(input.len());
+    let name = input[..end].to_ascii_lowercase();
+    let value;
+    match input[end..].strip_prefix('=') {
+      Some(input) if input.starts_with('"') => {
+        let mut unquoted = String::new();
+        let mut chars = input[1..].char_indices();
+        let mut after = "";
+        while let Some((i, c)) = chars.next() {
+          match c {
+            '\\' => unquoted.push(chars.next().map_or('\\', |(_, c)| c)),
+            '"' => {
+              after = &input[i + 2..];
+              break;
+            },
+            c => unquoted.push(c),
+          }
+        }
+        value = unquoted;
+        rest = after.split_once(';').map(|(_, rest)| rest);
+      },
+      Some(input) => {
+        let (raw, after) = input.split_once(';').map_or((input, None), |(raw, rest)| (raw, Some(rest)));
+        value = raw.trim_end_matches([' ', '\t', '\r', '\n']).to_string();
+        rest = after;
+        if value.is_empty() {
+          continue;
+        }
+      },
+      None => {
+        rest = input[end..].strip_prefix(';');
+        continue;
+      },
+    }
+    let valid_value = value.chars().all(|c| c == '\t' || (' '..='~').contains(&c) || ('\u{80}'..='\u{ff}').contains(&c));
+    if is_token(&name) && valid_value && !params.iter().any(|(key, _)| *key == name) {
+      params.push((name, value));
+    }
+  }
+  let mut serialized = format!("{}/{}", kind.to_ascii_lowercase(), subtype.to_ascii_lowercase());
+  for (name, value) in params {
+    if is_token(&value) {
+      serialized.push_str(&format!(";{}={}", name, value));
+    } else {
+      serialized.push_str(&format!(";{}=\"{}\"", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+  }
+  Some(serialized)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn guess() {
+    assert_eq!(from_path(Path::new("/tmp/report.PDF")), Some("application/pdf"));
+    assert_eq!(from_path(Path::new("archive.tar.gz")), Some("application/gzip"));
+    assert_eq!(from_path(Path::new("Makefile")), None);
+  }
+
+  #[test]
+  fn parse_mime() {
+    assert_eq!(parse(" Text/HTML ; Charset=UTF-8 ").as_deref(), Some("text/html;charset=UTF-8"));
+    assert_eq!(parse("text/plain;a=\"b;c\";d;e=;a=x").as_deref(), Some("text/plain;a=\"b;c\""));
+    assert_eq!(parse("text/plain;name=\"a\\\"b\"").as_deref(), Some("text/plain;name=\"a\\\"b\""));
+    assert_eq!(parse("text"), None);
+    assert_eq!(parse("te xt/plain"), None);
+  }
+}
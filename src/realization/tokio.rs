@@ -1,18 +1,120 @@
+mod connection;
+mod http1;
+#[cfg(feature = "http2")]
+mod http2;
+mod pool;
+mod redirect;
 mod response;
+mod scheme;
+#[cfg(target_os = "linux")]
+mod sendfile;
+#[cfg(feature = "tls")]
+mod tls;
 
-use crate::request_init::RequestInit;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use futures_util::StreamExt;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+use crate::abort_controller::AbortSignal;
+use crate::form_data::FormDataError;
+use crate::event_target::{AddEventListenerOptions, EventTarget};
+use crate::request::{Request, RequestError, RequestInfo};
+use crate::request_init::*;
+use crate::streams::{AbortError, ReadableStream};
 use crate::url::URL;
+use connection::{Connection, Connector, Lease, Transport};
 use thiserror::Error;
 
+pub use pool::{ConnectionPool, PoolOptions, PoolStats};
 pub use response::Response;
+#[cfg(feature = "http2")]
+pub use http2::Http2Options;
+#[cfg(feature = "tls")]
+pub use tls::TlsOptions;
 
 #[derive(Debug, Error)]
-pub enum FetchError {}
+pub enum FetchError {
+  #[error("io error: {0}")]
+  Io(#[source] std::io::Error),
+  #[error(transparent)]
+  Request(#[from] RequestError),
+  #[error("invalid request: {0}")]
+  InvalidRequest(String),
+  #[error("unsupported protocol: {0}")]
+  UnsupportedProtocol(String),
+  #[error("invalid response: {0}")]
+  InvalidResponse(String),
+  #[error("network error: {0}")]
+  Network(String),
+  #[error("redirect error: {0}")]
+  Redirect(String),
+  #[error("request aborted: {}", .0.as_deref().unwrap_or("no reason"))]
+  Aborted(Option<String>),
+  #[error("request timed out after {0:?}")]
+  Timeout(Duration),
+  #[error("body has already been used")]
+  BodyUsed,
+  #[error("tls error: {0}")]
+  Tls(String),
+  #[error("invalid form data: {0}")]
+  FormData(#[source] FormDataError),
+  #[error("http/2 error: {0}")]
+  Http2(String),
+}
+
+impl From<std::io::Error> for FetchError {
+  fn from(e: std::io::Error) -> Self {
+    if e.get_ref().is_some_and(|inner| inner.is::<FetchError>()) {
+      return *e.into_inner().unwrap().downcast::<FetchError>().unwrap();
+    }
+    if let Some(e) = AbortError::from_io(&e) {
+      return FetchError::Aborted(e.reason.clone());
+    }
+    #[cfg(feature = "tls")]
+    if let Some(e) = e.get_ref().and_then(|inner| inner.downcast_ref::<rustls::Error>()) {
+      return FetchError::Tls(e.to_string());
+    }
+    FetchError::Io(e)
+  }
+}
+
+impl From<FormDataError> for FetchError {
+  fn from(e: FormDataError) -> Self {
+    match e {
+      FormDataError::Io(e) => e.into(),
+      e => FetchError::FormData(e),
+    }
+  }
+}
+
+#[cfg(feature = "http2")]
+impl From<h2::Error> for FetchError {
+  fn from(e: h2::Error) -> Self {
+    let message = e.to_string();
+    match e.into_io() {
+      Some(e) => e.into(),
+      None => FetchError::Http2(message),
+    }
+  }
+}
+
+impl From<FetchError> for std::io::Error {
+  fn from(e: FetchError) -> Self {
+    match e {
+      FetchError::Io(e) => e,
+      e => std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+    }
+  }
+}
 
 /// 向服务器发送请求并获取响应
 ///
+/// `input`可以是`URL`、URL字符串或者`Request`，`init`中设置的字段会覆盖`Request`的对应字段
+///
 /// # Example
-/// ```
+/// ```no_run
 /// use fetch_js::url::URL;
 /// use fetch_js::request_init::RequestInit;
 /// use fetch_js::fetch;
@@ -26,7 +128,900 @@ pub enum FetchError {}
 ///   println!("{}", text);
 ///   Ok(())
 /// }
-pub async fn fetch(input: URL, init: RequestInit) -> Result<Response, FetchError> {
-  todo!()
+/// ```
+pub async fn fetch(input: impl Into<RequestInfo>, init: RequestInit) -> Result<Response, FetchError> {
+  let request = Request::new(input, init)?;
+  let signal = request.signal().clone();
+  if signal.aborted() {
+    return Err(FetchError::Aborted(signal.reason()));
+  }
+  let timeout = request.timeout();
+  let task = async move {
+    match timeout {
+      Some(timeout) => tokio::time::timeout(timeout, fetch_request(request)).await
+        .unwrap_or(Err(FetchError::Timeout(timeout))),
+      None => fetch_request(request).await,
+    }
+  };
+  let mut response = tokio::select! {
+    result = task => result?,
+    reason = wait_for_abort(&signal) => return Err(FetchError::Aborted(reason)),
+  };
+  response.abort_body_on(&signal);
+  Ok(response)
+}
+
+/// 等待信号中止并返回中止原因，返回前会移除注册的监听器
+async fn wait_for_abort(signal: &AbortSignal) -> Option<String> {
+  struct ListenerGuard(EventTarget, usize);
+  impl Drop for ListenerGuard {
+    fn drop(&mut self) {
+      self.0.remove_event_listener("abort", self.1);
+    }
+  }
+
+  let notify = Arc::new(Notify::new());
+  let waker = notify.clone();
+  let id = signal.add_event_listener("abort", move |_| waker.notify_one(), AddEventListenerOptions {
+    once: true,
+    signal: None,
+  });
+  let _guard = id.map(|id| ListenerGuard((**signal).clone(), id));
+  if !signal.aborted() {
+    notify.notified().await;
+  }
+  signal.reason()
+}
+
+async fn fetch_request(mut request: Request) -> Result<Response, FetchError> {
+  // data: URL不受同源限制，也不会发出网络请求
+  if request.url().get_protocol() == "data:" {
+    return scheme::fetch_data_url(request.url());
+  }
+  let referrer = match request.referrer().as_str() {
+    "" | "about:client" => None,
+    referrer => Some(URL::new(referrer)),
+  };
+  if request.mode() == RequestMode::SameOrigin {
+    if let Some(ref referrer) = referrer {
+      if referrer.get_origin() != request.url().get_origin() {
+        return Err(FetchError::Network(format!("request to {} is not same-origin", request.url().get_origin())));
+      }
+    }
+  }
+  if request.cache() == RequestCache::OnlyIfCached {
+    return Err(FetchError::Network("no cached response is available".to_string()));
+  }
+  match request.url().get_protocol().as_str() {
+    "file:" => return scheme::fetch_file_url(&request).await,
+    "blob:" => return scheme::fetch_blob_url(&request),
+    _ => {},
+  }
+
+  let connector = Connector::new(&request);
+  let max_redirects = request.max_redirects().unwrap_or(redirect::DEFAULT_MAX_REDIRECTS);
+  let mut url = request.url().clone();
+  let mut method = request.method().clone();
+  let mut headers = request.headers().clone();
+  let mut body = request.take_body()?;
+  let mut redirect_count = 0;
+  loop {
+    let request_headers = request_headers(&url, headers.clone(), body.as_ref(), &request, referrer.as_ref());
+    // 流式正文发送后就无法再次发送
+    let request_body = match body {
+      Some(ref init) if !init.is_stream() => init.try_clone(),
+      _ => body.take(),
+    };
+    let streamed = request_body.as_ref().is_some_and(BodyInit::is_stream);
+    let mut response = send(&connector, &url, &method, &request_headers, request_body).await?;
+    response.set_redirected(redirect_count > 0);
+    if !redirect::is_redirect(response.status()) {
+      return Ok(response);
+    }
+    let location = match response.headers().get("location") {
+      Some(location) => location.clone(),
+      None => return Ok(response),
+    };
+    match request.redirect() {
+      RequestRedirect::Manual => return Ok(response),
+      RequestRedirect::Error => {
+        return Err(FetchError::Redirect(format!("unexpected redirect to {}", location)));
+      },
+      RequestRedirect::Follow => {},
+    }
+    if redirect_count >= max_redirects {
+      return Err(FetchError::Redirect(format!("exceeded {} redirects", max_redirects)));
+    }
+    let mut next = URL::new_with_base(&location, &url);
+    if next.get_hash().is_none() {
+      if let Some(hash) = url.get_hash() {
+        next.set_hash(&hash);
+      }
+    }
+    if next.get_protocol() != "http:" && next.get_protocol() != "https:" {
+      return Err(FetchError::Network(format!("cannot redirect to {}", next.get_href())));
+    }
+    if request.mode() == RequestMode::SameOrigin && redirect::crosses_origin(&url, &next) {
+      return Err(FetchError::Network(format!("redirect to {} is not same-origin", next.get_origin())));
+    }
+    if redirect::rewrites_to_get(response.status(), &method) {
+      method = Method::GET;
+      body = None;
+      redirect::remove_body_headers(&mut headers);
+    } else if streamed {
+      return Err(FetchError::Redirect("cannot follow a redirect after sending a streaming body".to_string()));
+    }
+    redirect::strip_headers(&mut headers, &url, &next, request.redirect_header_filter());
+    url = next;
+    redirect_count += 1;
+  }
+}
+
+/// 发送一次请求并读取响应头
+async fn send(
+  connector: &Connector,
+  url: &URL,
+  method: &Method,
+  headers: &HashMap<String, String>,
+  body: Option<BodyInit>,
+) -> Result<Response, FetchError> {
+  match connector.connect(url).await? {
+    Transport::Http1(stream, lease) => send_http1(stream, lease, url, method, headers, body).await,
+    #[cfg(feature = "http2")]
+    Transport::Http2(sender, id) => send_http2(connector, sender, id, url, method, headers, body).await,
+  }
+}
+
+/// 在HTTP/2连接上发送请求，服务器没有处理就拒绝了请求时在新的连接上重试一次
+#[cfg(feature = "http2")]
+async fn send_http2(
+  connector: &Connector,
+  sender: http2::Sender,
+  id: u64,
+  url: &URL,
+  method: &Method,
+  headers: &HashMap<String, String>,
+  body: Option<BodyInit>,
+) -> Result<Response, FetchError> {
+  let refused = || FetchError::Network(format!("request to {} was refused by the server", url.get_href()));
+  // 流式正文发送后就无法重试
+  let replay = match body {
+    Some(ref body) => body.try_clone().map(Some),
+    None => Some(None),
+  };
+  if let Some(response) = http2::send(sender, url, method, headers, body).await? {
+    return Ok(response);
+  }
+  connector.discard_http2(url, id)?;
+  let body = replay.ok_or_else(refused)?;
+  match connector.connect(url).await? {
+    Transport::Http1(stream, lease) => send_http1(stream, lease, url, method, headers, body).await,
+    Transport::Http2(sender, id) => match http2::send(sender, url, method, headers, body).await? {
+      Some(response) => Ok(response),
+      None => {
+        connector.discard_http2(url, id)?;
+        Err(refused())
+      },
+    },
+  }
+}
+
+async fn send_http1(
+  mut stream: Connection,
+  lease: Option<Lease>,
+  url: &URL,
+  method: &Method,
+  headers: &HashMap<String, String>,
+  body: Option<BodyInit>,
+) -> Result<Response, FetchError> {
+  let head = http1::encode_request_head(method.as_str(), url, &header_sort(headers));
+  stream.write_all(&head).await?;
+  if let Some(body) = body {
+    write_body(&mut stream, body).await?;
+  }
+
+  let mut buffer = Vec::new();
+  let head = http1::read_response_head(&mut stream, &mut buffer).await?;
+  let framing = http1::BodyFraming::of_response(method, &head)?;
+  let closing = headers.iter()
+    .any(|(name, value)| name.eq_ignore_ascii_case("connection") && value.to_ascii_lowercase().contains("close"));
+  let lease = lease.filter(|_| head.keep_alive && !closing);
+  let body = http1::BodyDecoder::new(stream, buffer, framing);
+  let trailers = body.trailers();
+  let body = match lease {
+    // 没有正文时不必等到读取正文才放回连接
+    Some(lease) if framing == http1::BodyFraming::Empty => {
+      if let Some(stream) = body.into_reusable() {
+        lease.release(stream);
+      }
+      ReadableStream::from_stream(futures_util::stream::empty())
+    },
+    Some(lease) => body.into_stream(|stream| lease.release(stream)),
+    None => body.into_stream(drop),
+  };
+  Ok(Response::new(head, url.clone(), body, trailers))
+}
+
+/// 写入请求正文，长度未知的流使用chunked编码
+async fn write_body(stream: &mut Connection, body: BodyInit) -> Result<(), FetchError> {
+  let (mut chunks, length) = match body {
+    BodyInit::Text(text) => return Ok(stream.write_all(text.as_bytes()).await?),
+    BodyInit::Bytes(bytes) => return Ok(stream.write_all(&bytes).await?),
+    BodyInit::Stream { stream, length } => (stream, length),
+    body @ (BodyInit::FormData { .. } | BodyInit::Blob(_)) => {
+      let length = body.len();
+      (body.into_stream(), length)
+    },
+    BodyInit::File { file, offset, length, .. } => return write_file(stream, file, offset, length).await,
+  };
+  let mut written = 0;
+  while let Some(chunk) = chunks.next().await {
+    let chunk = chunk?;
+    written += chunk.len() as u64;
+    match length {
+      Some(length) if written > length => break,
+      Some(_) => stream.write_all(&chunk).await?,
+      None if chunk.is_empty() => {},
+      None => stream.write_all(&http1::encode_chunk(&chunk)).await?,
+    }
+  }
+  match length {
+    Some(length) if written != length => {
+      Err(FetchError::InvalidRequest(format!("body length {} does not match Content-Length {}", written, length)))
+    },
+    Some(_) => Ok(()),
+    None => Ok(stream.write_all(http1::LAST_CHUNK).await?),
+  }
+}
+
+/// 发送文件正文，Linux上的明文连接优先使用`sendfile`避免在用户态复制数据
+async fn write_file(stream: &mut Connection, file: std::fs::File, offset: u64, length: u64) -> Result<(), FetchError> {
+  #[cfg(target_os = "linux")]
+  if let Some(tcp) = stream.as_tcp() {
+    if sendfile::send_file(tcp, &file, offset, length).await? {
+      return Ok(());
+    }
+  }
+  let body = BodyInit::File { file, offset, length, content_type: None };
+  let written = tokio::io::copy(&mut body.into_stream().into_async_read(), stream).await?;
+  if written != length {
+    return Err(FetchError::InvalidRequest(format!("body length {} does not match Content-Length {}", written, length)));
+  }
+  Ok(())
 }
 
+fn request_headers(
+  url: &URL,
+  mut headers: HashMap<String, String>,
+  body: Option<&BodyInit>,
+  request: &Request,
+  referrer: Option<&URL>,
+) -> HashMap<String, String> {
+  let mut set_default = |name: &str, value: String| {
+    if !has_header(&headers, name) {
+      headers.insert(name.to_string(), value);
+    }
+  };
+  set_default("Host", url.get_host());
+  set_default("Accept", "*/*".to_string());
+  set_default("Connection", if request.keepalive() { "keep-alive" } else { "close" }.to_string());
+  match body.map(BodyInit::len) {
+    Some(Some(length)) => set_default("Content-Length", length.to_string()),
+    Some(None) => set_default("Transfer-Encoding", "chunked".to_string()),
+    None => {},
+  }
+  if let Some(content_type) = body.and_then(BodyInit::content_type) {
+    set_default("Content-Type", content_type);
+  }
+  if request.credentials() != RequestCredentials::Omit {
+    if let Some(username) = url.get_username() {
+      let credentials = format!("{}:{}", username, url.get_password().unwrap_or_default());
+      set_default("Authorization", format!("Basic {}", crate::base64::encode(credentials.as_bytes())));
+    }
+  }
+  match request.cache() {
+    RequestCache::NoStore | RequestCache::Reload => {
+      set_default("Pragma", "no-cache".to_string());
+      set_default("Cache-Control", "no-cache".to_string());
+    },
+    RequestCache::NoCache => set_default("Cache-Control", "max-age=0".to_string()),
+    _ => {},
+  }
+  if let Some(referrer) = referrer {
+    if let Some(value) = request.referrer_policy().unwrap_or_default().referrer(referrer, url) {
+      set_default("Referer", value);
+    }
+  }
+  headers
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tokio::io::AsyncReadExt;
+  use tokio::net::TcpListener;
+
+  /// 启动一个依次处理每个响应的本地服务器，返回其地址和收到的请求
+  async fn serve(responses: Vec<&'static str>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let handle = tokio::spawn(async move {
+      let mut requests = vec![];
+      for response in responses {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+          let n = socket.read(&mut buffer).await.unwrap();
+          request.extend_from_slice(&buffer[..n]);
+        }
+        socket.write_all(response.as_bytes()).await.unwrap();
+        requests.push(String::from_utf8(request).unwrap());
+      }
+      requests
+    });
+    (addr, handle)
+  }
+
+  /// 启动一个支持keep-alive的本地服务器，每个连接最多处理`limit`个请求，响应正文为连接的序号；
+  /// 请求带有`Connection: close`时响应后关闭连接
+  async fn serve_keep_alive(limit: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+      for index in 0.. {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        tokio::spawn(async move {
+          let mut request = Vec::new();
+          let mut buffer = [0; 1024];
+          for _ in 0..limit {
+            let end = loop {
+              if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+              }
+              match socket.read(&mut buffer).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => request.extend_from_slice(&buffer[..n]),
+              }
+            };
+            let head = String::from_utf8_lossy(&request[..end]).to_ascii_lowercase();
+            request.drain(..end);
+            let close = head.contains("connection: close");
+            let body = format!("connection {}", index);
+            let mut response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n", body.len());
+            if close {
+              response.push_str("Connection: close\r\n");
+            }
+            response.push_str("\r\n");
+            if !head.starts_with("head ") {
+              response.push_str(&body);
+            }
+            socket.write_all(response.as_bytes()).await.unwrap();
+            if close {
+              return;
+            }
+          }
+        });
+      }
+    });
+    addr
+  }
+
+  /// 启动一个按`Content-Length`读取完整请求正文的本地服务器，每个请求都返回204
+  async fn serve_uploads(count: usize) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let handle = tokio::spawn(async move {
+      let mut requests = vec![];
+      for _ in 0..count {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        let length = loop {
+          let n = socket.read(&mut buffer).await.unwrap();
+          request.extend_from_slice(&buffer[..n]);
+          if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&request[..end]).to_lowercase();
+            let length: usize = head.split("content-length: ").nth(1).unwrap().split("\r\n").next().unwrap().parse().unwrap();
+            break end + 4 + length;
+          }
+        };
+        while request.len() < length {
+          let n = socket.read(&mut buffer).await.unwrap();
+          request.extend_from_slice(&buffer[..n]);
+        }
+        socket.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await.unwrap();
+        requests.push(String::from_utf8(request).unwrap());
+      }
+      requests
+    });
+    (addr, handle)
+  }
+
+  #[tokio::test]
+  async fn fetch_text() {
+    let (addr, handle) = serve(vec!["HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nhello"]).await;
+    let mut response = fetch(URL::new(&format!("http://{}/hello?a=b", addr)), RequestInit::default()).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.ok());
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/plain");
+    assert_eq!(response.text().await.unwrap(), "hello");
+    assert!(response.body_used());
+    assert!(matches!(response.bytes().await, Err(FetchError::BodyUsed)));
+    let requests = handle.await.unwrap();
+    assert!(requests[0].starts_with("GET /hello?a=b HTTP/1.1\r\n"));
+  }
+
+  #[tokio::test]
+  async fn cache_and_referrer_headers() {
+    let (addr, handle) = serve(vec!["HTTP/1.1 204 No Content\r\n\r\n"]).await;
+    let init = RequestInit {
+      cache: Some(RequestCache::NoStore),
+      referrer: Some("https://example.com/page?q=1".to_string()),
+      referrer_policy: Some(ReferrerPolicy::Origin),
+      ..Default::default()
+    };
+    fetch(URL::new(&format!("http://{}/", addr)), init).await.unwrap();
+    let request = &handle.await.unwrap()[0];
+    assert!(request.contains("Cache-Control: no-cache\r\n"));
+    assert!(request.contains("Pragma: no-cache\r\n"));
+    assert!(request.contains("Referer: https://example.com/\r\n"));
+  }
+
+  #[tokio::test]
+  async fn reject_invalid_options() {
+    let url = URL::new("http://127.0.0.1:1/");
+    let init = RequestInit {
+      mode: Some(RequestMode::NoCors),
+      method: Some(Method::PUT),
+      ..Default::default()
+    };
+    assert!(matches!(fetch(url.clone(), init).await, Err(FetchError::Request(RequestError::Invalid(_)))));
+    let init = RequestInit {
+      cache: Some(RequestCache::OnlyIfCached),
+      ..Default::default()
+    };
+    assert!(matches!(fetch(url, init).await, Err(FetchError::Request(RequestError::Invalid(_)))));
+  }
+
+  #[tokio::test]
+  async fn follow_redirects() {
+    let (addr, handle) = serve(vec![
+      "HTTP/1.1 302 Found\r\nLocation: /next\r\n\r\n",
+      "HTTP/1.1 307 Temporary Redirect\r\nLocation: final?x=1\r\n\r\n",
+      "HTTP/1.1 200 OK\r\n\r\ndone",
+    ]).await;
+    let init = RequestInit {
+      method: Some(Method::POST),
+      headers: Some(HashMap::from([("Content-Type".to_string(), "text/plain".to_string())])),
+      body: Some("payload".into()),
+      ..Default::default()
+    };
+    let mut response = fetch(URL::new(&format!("http://{}/start", addr)), init).await.unwrap();
+    assert!(response.redirected());
+    assert_eq!(response.url().get_href(), format!("http://{}/final?x=1", addr));
+    assert_eq!(response.text().await.unwrap(), "done");
+    let requests = handle.await.unwrap();
+    assert!(requests[0].starts_with("POST /start HTTP/1.1\r\n"));
+    assert!(requests[1].starts_with("GET /next HTTP/1.1\r\n"));
+    assert!(!requests[1].contains("Content-Type"));
+    assert!(requests[2].starts_with("GET /final?x=1 HTTP/1.1\r\n"));
+  }
+
+  #[tokio::test]
+  async fn cross_origin_redirect_headers() {
+    let (target, handle) = serve(vec!["HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"]).await;
+    let port = target.rsplit(':').next().unwrap();
+    let location = format!("HTTP/1.1 307 Temporary Redirect\r\nLocation: http://localhost:{}/next\r\n\r\n", port);
+    let (addr, _) = serve(vec![Box::leak(location.into_boxed_str())]).await;
+    let init = RequestInit::builder()
+      .method(Method::POST)
+      .header("Host", "internal.example.com")
+      .header("Content-Type", "application/x-custom")
+      .header("Authorization", "Bearer secret")
+      .body("payload")
+      .build()
+      .unwrap();
+    fetch(URL::new(&format!("http://{}/", addr)), init).await.unwrap();
+    let request = handle.await.unwrap().remove(0).to_ascii_lowercase();
+    assert!(request.starts_with("post /next http/1.1\r\n"));
+    assert!(request.contains(&format!("\r\nhost: localhost:{}\r\n", port)));
+    assert!(request.contains("\r\ncontent-length: 7\r\n"));
+    assert!(request.contains("\r\ncontent-type: application/x-custom\r\n"));
+    assert!(!request.contains("internal.example.com"));
+    assert!(!request.contains("authorization"));
+  }
+
+  #[tokio::test]
+  async fn redirect_modes() {
+    let (addr, _handle) = serve(vec![
+      "HTTP/1.1 301 Moved Permanently\r\nLocation: /a\r\n\r\n",
+      "HTTP/1.1 301 Moved Permanently\r\nLocation: /a\r\n\r\n",
+      "HTTP/1.1 301 Moved Permanently\r\nLocation: /a\r\n\r\n",
+      "HTTP/1.1 301 Moved Permanently\r\nLocation: /a\r\n\r\n",
+    ]).await;
+    let url = URL::new(&format!("http://{}/", addr));
+    let init = RequestInit {
+      redirect: Some(RequestRedirect::Manual),
+      ..Default::default()
+    };
+    let response = fetch(url.clone(), init).await.unwrap();
+    assert_eq!(response.status(), 301);
+    assert!(!response.redirected());
+    let init = RequestInit {
+      redirect: Some(RequestRedirect::Error),
+      ..Default::default()
+    };
+    assert!(matches!(fetch(url.clone(), init).await, Err(FetchError::Redirect(_))));
+    let init = RequestInit {
+      max_redirects: Some(1),
+      ..Default::default()
+    };
+    assert!(matches!(fetch(url, init).await, Err(FetchError::Redirect(_))));
+
+    // same-origin模式下可以跟随同源的重定向，但不能离开原来的源
+    let (addr, handle) = serve(vec![
+      "HTTP/1.1 302 Found\r\nLocation: /same\r\n\r\n",
+      "HTTP/1.1 302 Found\r\nLocation: http://example.invalid/\r\n\r\n",
+    ]).await;
+    let init = RequestInit {
+      mode: Some(RequestMode::SameOrigin),
+      ..Default::default()
+    };
+    let result = fetch(URL::new(&format!("http://{}/", addr)), init).await;
+    assert!(matches!(result, Err(FetchError::Network(ref message)) if message.contains("example.invalid")));
+    assert_eq!(handle.await.unwrap().len(), 2);
+  }
+
+  #[tokio::test]
+  async fn timeout_and_abort() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+      let mut sockets = vec![];
+      while let Ok((socket, _)) = listener.accept().await {
+        sockets.push(socket);
+      }
+    });
+    let url = URL::new(&format!("http://{}/slow", addr));
+    let init = RequestInit::builder().timeout(Duration::from_millis(50)).build().unwrap();
+    assert!(matches!(fetch(url.clone(), init).await, Err(FetchError::Timeout(_))));
+
+    let controller = crate::abort_controller::AbortController::new();
+    let init = RequestInit::builder().signal(controller.signal.clone()).build().unwrap();
+    let pending = tokio::spawn(fetch(url, init));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    controller.abort(Some("cancelled".to_string()));
+    let result = pending.await.unwrap();
+    assert!(matches!(result, Err(FetchError::Aborted(Some(ref reason))) if reason == "cancelled"));
+    assert_eq!(controller.signal.listener_count("abort"), 0);
+  }
+
+  #[tokio::test]
+  async fn abort_while_reading_body() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+      let (mut socket, _) = listener.accept().await.unwrap();
+      let mut buffer = [0; 1024];
+      let _ = socket.read(&mut buffer).await.unwrap();
+      socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\npartial").await.unwrap();
+      tokio::time::sleep(Duration::from_secs(10)).await;
+    });
+    let controller = crate::abort_controller::AbortController::new();
+    let init = RequestInit::builder().signal(controller.signal.clone()).build().unwrap();
+    let mut response = fetch(URL::new(&format!("http://{}/", addr)), init).await.unwrap();
+    let mut reader = response.body().unwrap().get_reader();
+    assert_eq!(reader.read().await.unwrap().unwrap(), "partial");
+    controller.abort(Some("enough".to_string()));
+    let e = reader.read().await.unwrap_err();
+    assert!(matches!(FetchError::from(e), FetchError::Aborted(Some(ref reason)) if reason == "enough"));
+  }
+
+  #[tokio::test]
+  async fn keepalive_reuses_connections() {
+    let url = URL::new(&format!("http://{}/", serve_keep_alive(usize::MAX).await));
+    let pool = ConnectionPool::new(PoolOptions::default());
+    let init = || RequestInit::builder().keepalive(true).pool(pool.clone());
+    let text = |init: RequestInit| {
+      let url = url.clone();
+      async move { fetch(url, init).await.unwrap().text().await.unwrap() }
+    };
+    for _ in 0..3 {
+      assert_eq!(text(init().build().unwrap()).await, "connection 0");
+    }
+    assert_eq!(pool.stats(), PoolStats { idle: 1, created: 1, reused: 2, evicted: 0 });
+
+    // 没有正文的响应立即放回连接
+    let response = fetch(url.clone(), init().method(Method::HEAD).build().unwrap()).await.unwrap();
+    assert_eq!(pool.stats().idle, 1);
+    drop(response);
+
+    // 正文没有读完的连接不会放回
+    let response = fetch(url.clone(), init().build().unwrap()).await.unwrap();
+    drop(response);
+    assert_eq!(pool.stats().idle, 0);
+    assert_eq!(text(init().build().unwrap()).await, "connection 1");
+
+    // 请求要求关闭连接时不放回
+    assert_eq!(text(init().header("Connection", "close").build().unwrap()).await, "connection 1");
+    assert_eq!(pool.stats(), PoolStats { idle: 0, created: 2, reused: 5, evicted: 0 });
+
+    // 没有设置keepalive时不使用连接池
+    assert_eq!(text(RequestInit::default()).await, "connection 2");
+    assert_eq!(pool.stats().created, 2);
+  }
+
+  #[tokio::test]
+  async fn keepalive_health_check() {
+    // 服务器处理一个请求后就关闭连接，但没有在响应中说明
+    let url = URL::new(&format!("http://{}/", serve_keep_alive(1).await));
+    let pool = ConnectionPool::new(PoolOptions::default());
+    let init = || RequestInit::builder().keepalive(true).pool(pool.clone()).build().unwrap();
+    assert_eq!(fetch(url.clone(), init()).await.unwrap().text().await.unwrap(), "connection 0");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(fetch(url.clone(), init()).await.unwrap().text().await.unwrap(), "connection 1");
+    assert_eq!(pool.stats(), PoolStats { idle: 1, created: 2, reused: 0, evicted: 1 });
+
+    pool.set_options(PoolOptions { idle_timeout: Duration::from_millis(20), ..Default::default() });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(fetch(url, init()).await.unwrap().text().await.unwrap(), "connection 2");
+    assert_eq!(pool.stats().evicted, 2);
+    pool.clear();
+    assert_eq!(pool.stats(), PoolStats { idle: 0, created: 3, reused: 0, evicted: 3 });
+  }
+
+  #[tokio::test]
+  async fn keep_alive_body_framing() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+      let (mut socket, _) = listener.accept().await.unwrap();
+      let mut buffer = [0; 1024];
+      let _ = socket.read(&mut buffer).await.unwrap();
+      socket.write_all(b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n").await.unwrap();
+      socket.write_all(b"6\r\nhello \r\n5\r\nworld\r\n0\r\nX-Checksum: 1\r\n\r\n").await.unwrap();
+      // 保持连接不关闭，正文必须依靠分帧结束
+      tokio::time::sleep(Duration::from_secs(10)).await;
+    });
+    let mut response = fetch(URL::new(&format!("http://{}/", addr)), RequestInit::default()).await.unwrap();
+    let text = tokio::time::timeout(Duration::from_secs(1), response.text()).await.unwrap().unwrap();
+    assert_eq!(text, "hello world");
+    assert_eq!(response.trailers().get("x-checksum").unwrap(), "1");
+  }
+
+  #[tokio::test]
+  async fn text_split_across_reads() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+      let (mut socket, _) = listener.accept().await.unwrap();
+      let mut buffer = [0; 1024];
+      let _ = socket.read(&mut buffer).await.unwrap();
+      let body = "你好".as_bytes();
+      socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\n").await.unwrap();
+      socket.write_all(&body[..2]).await.unwrap();
+      socket.flush().await.unwrap();
+      tokio::time::sleep(Duration::from_millis(20)).await;
+      socket.write_all(&body[2..]).await.unwrap();
+    });
+    let mut response = fetch(URL::new(&format!("http://{}/", addr)), RequestInit::default()).await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "你好");
+  }
+
+  #[tokio::test]
+  async fn binary_body() {
+    let (addr, _handle) = serve(vec![
+      "HTTP/1.1 200 OK\r\nContent-Type: Application/Octet-Stream\r\nContent-Length: 4\r\n\r\n\u{0}\u{1}\u{2}\u{3}",
+    ]).await;
+    let mut response = fetch(URL::new(&format!("http://{}/", addr)), RequestInit::default()).await.unwrap();
+    assert!(!response.body_used());
+    let blob = response.blob().await.unwrap();
+    assert_eq!(blob.array_buffer().await.unwrap(), vec![0, 1, 2, 3]);
+    assert_eq!(blob.get_type(), "application/octet-stream");
+    assert!(matches!(response.array_buffer().await, Err(FetchError::BodyUsed)));
+  }
+
+  #[tokio::test]
+  async fn stream_body() {
+    let (addr, _handle) = serve(vec![
+      "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n3\r\ndef\r\n0\r\n\r\n",
+      "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nab",
+    ]).await;
+    let mut response = fetch(URL::new(&format!("http://{}/", addr)), RequestInit::default()).await.unwrap();
+    let mut reader = response.body().unwrap().into_async_read();
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body).await.unwrap();
+    assert_eq!(body, b"abcdef");
+    assert!(response.body().is_err());
+
+    let mut response = fetch(URL::new(&format!("http://{}/", addr)), RequestInit::default()).await.unwrap();
+    assert!(matches!(response.text().await, Err(FetchError::InvalidResponse(_))));
+  }
+
+  #[tokio::test]
+  async fn clone_response() {
+    let (addr, _handle) = serve(vec![
+      "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789",
+      "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789",
+    ]).await;
+    let url = URL::new(&format!("http://{}/", addr));
+    let mut response = fetch(url.clone(), RequestInit::default()).await.unwrap();
+    let mut copy = response.clone().unwrap();
+    assert_eq!(copy.status(), 200);
+    assert_eq!(copy.text().await.unwrap(), "0123456789");
+    assert_eq!(response.text().await.unwrap(), "0123456789");
+    assert!(matches!(response.clone(), Err(FetchError::BodyUsed)));
+
+    let mut response = fetch(url, RequestInit::default()).await.unwrap();
+    let mut copy = response.clone_with_limit(4).unwrap();
+    assert!(matches!(copy.text().await, Err(FetchError::Io(ref e)) if e.kind() == std::io::ErrorKind::OutOfMemory));
+    assert_eq!(response.text().await.unwrap(), "0123456789");
+  }
+
+  #[tokio::test]
+  async fn data_url() {
+    let mut response = fetch("data:text/plain;base64,SGVsbG8=", RequestInit::default()).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/plain");
+    assert_eq!(response.text().await.unwrap(), "Hello");
+
+    let init = RequestInit {
+      mode: Some(RequestMode::SameOrigin),
+      referrer: Some("http://example.com/".to_string()),
+      ..Default::default()
+    };
+    let mut response = fetch("data:application/json,%7B%22a%22%3A1%7D", init).await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "{\"a\":1}");
+    assert!(matches!(fetch("data:;base64,QQ=", RequestInit::default()).await, Err(FetchError::Network(_))));
+  }
+
+  #[tokio::test]
+  async fn file_url() {
+    let path = std::env::temp_dir().join(format!("fetch-js-url-{}.txt", std::process::id()));
+    std::fs::write(&path, "0123456789").unwrap();
+    let url = URL::new(&format!("file://{}", path.display()));
+    assert!(matches!(fetch(url.clone(), RequestInit::default()).await, Err(FetchError::Network(_))));
+
+    let init = || RequestInit::builder().allow_file_urls(true);
+    let mut response = fetch(url.clone(), init().build().unwrap()).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/plain");
+    assert_eq!(response.headers().get("content-length").unwrap(), "10");
+    assert_eq!(response.text().await.unwrap(), "0123456789");
+
+    let mut response = fetch(url.clone(), init().header("Range", "bytes=-3").build().unwrap()).await.unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.headers().get("content-range").unwrap(), "bytes 7-9/10");
+    assert_eq!(response.text().await.unwrap(), "789");
+
+    let response = fetch(url.clone(), init().header("Range", "bytes=20-").build().unwrap()).await.unwrap();
+    assert_eq!(response.status(), 416);
+    let mut response = fetch(url.clone(), init().method(Method::HEAD).build().unwrap()).await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "");
+    std::fs::remove_file(&path).unwrap();
+    assert!(fetch(url, init().build().unwrap()).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn blob_url() {
+    let blob = crate::blob::Blob::new("hello world", "Text/Plain");
+    let url = URL::create_object_url(&blob);
+    let mut response = fetch(format!("{}#frag", url), RequestInit::default()).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/plain");
+    assert_eq!(response.text().await.unwrap(), "hello world");
+
+    let init = RequestInit::builder().header("Range", "bytes=6-").build().unwrap();
+    let mut response = fetch(url.as_str(), init).await.unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.text().await.unwrap(), "world");
+
+    let init = RequestInit::builder().method(Method::POST).build().unwrap();
+    assert!(matches!(fetch(url.as_str(), init).await, Err(FetchError::Network(_))));
+    URL::revoke_object_url(&url);
+    assert!(matches!(fetch(url.as_str(), RequestInit::default()).await, Err(FetchError::Network(_))));
+  }
+
+  #[tokio::test]
+  async fn form_data_response() {
+    let (addr, _handle) = serve(vec![
+      concat!(
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/form-data; boundary=XyZ\r\nContent-Length: 136\r\n\r\n",
+        "--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n",
+        "--XyZ\r\nContent-Disposition: form-data; name=\"f\"; filename=\"f.txt\"\r\n\r\nhi\r\n--XyZ--\r\n",
+      ),
+      "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 3\r\n\r\na=1",
+    ]).await;
+    let url = URL::new(&format!("http://{}/", addr));
+    let mut response = fetch(url.clone(), RequestInit::default()).await.unwrap();
+    let form = response.form_data().await.unwrap();
+    assert_eq!(form.get("a").unwrap().as_text(), Some("1"));
+    assert_eq!(form.get("f").unwrap().as_file().unwrap().bytes().await.unwrap(), "hi");
+
+    let mut response = fetch(url, RequestInit::default()).await.unwrap();
+    let e = response.form_data().await.unwrap_err();
+    assert!(matches!(e, FetchError::FormData(FormDataError::UnsupportedContentType(_))));
+  }
+
+  #[tokio::test]
+  async fn stream_request_body() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+      let mut requests = vec![];
+      for response in ["HTTP/1.1 200 OK\r\n\r\n", "HTTP/1.1 307 Temporary Redirect\r\nLocation: /again\r\n\r\n"] {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !request.ends_with(b"0\r\n\r\n") {
+          let n = socket.read(&mut buffer).await.unwrap();
+          request.extend_from_slice(&buffer[..n]);
+        }
+        socket.write_all(response.as_bytes()).await.unwrap();
+        requests.push(String::from_utf8(request).unwrap());
+      }
+      // 长度不符的请求在读取响应前就会失败
+      let _socket = listener.accept().await.unwrap();
+      requests
+    });
+    let url = URL::new(&format!("http://{}/upload", addr));
+    let body = || BodyInit::from_reader(&b"hello world"[..]);
+    let init = || RequestInit::builder().method(Method::PUT).body(body());
+    let response = fetch(url.clone(), init().build().unwrap()).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let result = fetch(url.clone(), init().build().unwrap()).await;
+    assert!(matches!(result, Err(FetchError::Redirect(_))));
+    let init = RequestInit::builder().method(Method::PUT).body(body().with_length(5)).build().unwrap();
+    assert!(matches!(fetch(url, init).await, Err(FetchError::InvalidRequest(_))));
+
+    let requests = handle.await.unwrap();
+    assert!(requests[0].contains("Transfer-Encoding: chunked\r\n"));
+    assert!(!requests[0].contains("Content-Length"));
+    assert!(requests[0].ends_with("\r\n\r\nb\r\nhello world\r\n0\r\n\r\n"));
+  }
+
+  #[tokio::test]
+  async fn file_request_body() {
+    let (addr, handle) = serve_uploads(2).await;
+    let path = std::env::temp_dir().join(format!("fetch-js-upload-{}.json", std::process::id()));
+    let content = "0123456789".repeat(10_000);
+    std::fs::write(&path, &content).unwrap();
+    let url = URL::new(&format!("http://{}/upload", addr));
+    let body = BodyInit::from_file(&path).await.unwrap();
+    let init = RequestInit::builder().method(Method::PUT).body(body).build().unwrap();
+    assert_eq!(fetch(url.clone(), init).await.unwrap().status(), 204);
+    let body = BodyInit::from_file(&path).await.unwrap().with_range(5..15);
+    let init = RequestInit::builder().method(Method::PUT).body(body).header("Content-Type", "text/plain").build().unwrap();
+    fetch(url, init).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let requests = handle.await.unwrap();
+    assert!(requests[0].contains("Content-Type: application/json\r\n"));
+    assert!(requests[0].contains("Content-Length: 100000\r\n"));
+    assert!(requests[0].ends_with(&content));
+    assert!(requests[1].contains("Content-Type: text/plain\r\n"));
+    assert!(requests[1].ends_with("\r\n\r\n5678901234"));
+  }
+
+  #[tokio::test]
+  async fn form_data_body() {
+    let (addr, handle) = serve_uploads(1).await;
+    let path = std::env::temp_dir().join(format!("fetch-js-form-{}.txt", std::process::id()));
+    std::fs::write(&path, "file content").unwrap();
+    let mut form = crate::form_data::FormData::new();
+    form.append("name", "value");
+    form.append("upload", crate::blob::File::from_path(&path).await.unwrap());
+    let init = RequestInit::builder().method(Method::POST).body(form).build().unwrap();
+    fetch(URL::new(&format!("http://{}/form", addr)), init).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let request = handle.await.unwrap().remove(0);
+    let boundary = request.split("boundary=").nth(1).unwrap().split("\r\n").next().unwrap();
+    assert!(boundary.len() > 20);
+    assert!(request.contains(&format!("--{}\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nvalue\r\n", boundary)));
+    assert!(request.contains("filename=\"fetch-js-form-"));
+    assert!(request.contains("Content-Type: text/plain\r\n\r\nfile content\r\n"));
+    assert!(request.ends_with(&format!("--{}--\r\n", boundary)));
+  }
+}
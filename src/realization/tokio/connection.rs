@@ -0,0 +1,207 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use crate::request::Request;
+use crate::url::URL;
+use super::FetchError;
+use super::pool::ConnectionPool;
+
+/// 到服务器的连接，`https:`在TCP之上进行TLS握手
+pub(crate) enum Connection {
+  Tcp(TcpStream),
+  #[cfg(feature = "tls")]
+  Tls(Box<tokio_rustls::client::TlsStream<TcpStream>>),
+}
+
+/// 按请求的选项建立连接，跟随重定向时复用同一个`Connector`
+#[derive(Clone)]
+pub(crate) struct Connector {
+  pool: ConnectionPool,
+  /// HTTP/1.1连接只有`keepalive`请求才会放回连接池
+  keepalive: bool,
+  #[cfg(feature = "tls")]
+  tls: Option<super::TlsOptions>,
+}
+
+/// 用来发送请求的连接
+pub(crate) enum Transport {
+  Http1(Connection, Option<Lease>),
+  /// 连接池中共享的HTTP/2连接和它在连接池中的编号
+  #[cfg(feature = "http2")]
+  Http2(super::http2::Sender, u64),
+}
+
+/// 从连接池借出的连接，正文读完后通过`release`放回
+pub(crate) struct Lease {
+  pool: ConnectionPool,
+  key: String,
+  created: Instant,
+}
+
+impl Lease {
+  pub(crate) fn release(self, connection: Connection) {
+    self.pool.release(&self.key, connection, self.created);
+  }
+}
+
+impl Connector {
+  pub(crate) fn new(request: &Request) -> Self {
+    Self {
+      pool: request.pool().unwrap_or_else(|| ConnectionPool::global()).clone(),
+      keepalive: request.keepalive(),
+      #[cfg(feature = "tls")]
+      tls: request.tls().cloned(),
+    }
+  }
+
+  /// 按URL的协议建立连接，`https:`需要启用`tls`特性
+  ///
+  /// 优先复用这个源的HTTP/2连接，其次是`keepalive`请求可用的空闲HTTP/1.1连接
+  pub(crate) async fn connect(&self, url: &URL) -> Result<Transport, FetchError> {
+    #[cfg(feature = "tls")]
+    if url.get_protocol() == "https:" {
+      super::tls::load_default_roots().await;
+    }
+    let key = self.pool_key(url)?;
+    #[cfg(feature = "http2")]
+    if let Some((sender, id)) = self.pool.http2(&key) {
+      return Ok(Transport::Http2(sender, id));
+    }
+    if self.keepalive {
+      if let Some((connection, created)) = self.pool.checkout(&key) {
+        return Ok(Transport::Http1(connection, Some(Lease { pool: self.pool.clone(), key, created })));
+      }
+    }
+    #[cfg(feature = "http2")]
+    let connecting = if url.get_protocol() == "https:" || self.pool.options().http2.prior_knowledge {
+      // 等待其他请求建立的连接，它可能是可以共享的HTTP/2连接
+      let connecting = self.pool.lock_connecting(&key).await;
+      if let Some((sender, id)) = self.pool.http2(&key) {
+        return Ok(Transport::Http2(sender, id));
+      }
+      connecting
+    } else {
+      None
+    };
+    let connection = self.open(url).await?;
+    #[cfg(feature = "http2")]
+    if connection.is_http2(&self.pool.options().http2) {
+      let (sender, id) = super::http2::handshake(connection, self.pool.options(), self.pool.clone(), key).await?;
+      return Ok(Transport::Http2(sender, id));
+    }
+    // ALPN选择了HTTP/1.1，不必让其他请求等待
+    #[cfg(feature = "http2")]
+    if let Some(connecting) = connecting {
+      connecting.http1();
+    }
+    if !self.keepalive {
+      return Ok(Transport::Http1(connection, None));
+    }
+    self.pool.created();
+    Ok(Transport::Http1(connection, Some(Lease { pool: self.pool.clone(), key, created: Instant::now() })))
+  }
+
+  /// 不再使用被服务器拒绝请求的HTTP/2连接
+  #[cfg(feature = "http2")]
+  pub(crate) fn discard_http2(&self, url: &URL, id: u64) -> Result<(), FetchError> {
+    self.pool.remove_http2(&self.pool_key(url)?, id);
+    Ok(())
+  }
+
+  async fn open(&self, url: &URL) -> Result<Connection, FetchError> {
+    match url.get_protocol().as_str() {
+      "http:" => Ok(Connection::Tcp(connect_tcp(url).await?)),
+      #[cfg(feature = "tls")]
+      "https:" => {
+        let stream = super::tls::handshake(connect_tcp(url).await?, &url.get_hostname(), self.tls_options().client_config()?).await?;
+        Ok(Connection::Tls(Box::new(stream)))
+      },
+      protocol => Err(FetchError::UnsupportedProtocol(protocol.to_string())),
+    }
+  }
+
+  /// 协议、主机和端口相同的连接才能互相替代，`https:`还要求TLS配置相同
+  fn pool_key(&self, url: &URL) -> Result<String, FetchError> {
+    let port = url.get_effective_port()
+      .ok_or_else(|| FetchError::InvalidRequest(format!("invalid port: {:?}", url.get_port())))?;
+    let key = format!("{}//{}:{}", url.get_protocol(), url.get_hostname(), port);
+    #[cfg(feature = "tls")]
+    let key = match url.get_protocol().as_str() {
+      "https:" => format!("{}#{}", key, self.tls_options().id()),
+      _ => key,
+    };
+    Ok(key)
+  }
+
+  #[cfg(feature = "tls")]
+  fn tls_options(&self) -> &super::TlsOptions {
+    self.tls.as_ref().unwrap_or_else(|| super::tls::default_options())
+  }
+}
+
+impl Connection {
+  /// 是否使用HTTP/2：`https:`看ALPN协商的结果，`http:`需要设置`prior_knowledge`
+  #[cfg(feature = "http2")]
+  fn is_http2(&self, options: &super::http2::Http2Options) -> bool {
+    match self {
+      Connection::Tcp(_) => options.prior_knowledge,
+      #[cfg(feature = "tls")]
+      Connection::Tls(stream) => stream.get_ref().1.alpn_protocol() == Some(b"h2".as_slice()),
+    }
+  }
+
+  /// 明文连接底层的TCP流，加密连接返回`None`
+  #[cfg(target_os = "linux")]
+  pub(crate) fn as_tcp(&self) -> Option<&TcpStream> {
+    match self {
+      Connection::Tcp(stream) => Some(stream),
+      #[cfg(feature = "tls")]
+      Connection::Tls(_) => None,
+    }
+  }
+}
+
+async fn connect_tcp(url: &URL) -> Result<TcpStream, FetchError> {
+  let port = url.get_effective_port()
+    .ok_or_else(|| FetchError::InvalidRequest(format!("invalid port: {:?}", url.get_port())))?;
+  Ok(TcpStream::connect((url.get_hostname(), port)).await?)
+}
+
+impl AsyncRead for Connection {
+  fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+      Connection::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+      #[cfg(feature = "tls")]
+      Connection::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+    }
+  }
+}
+
+impl AsyncWrite for Connection {
+  fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+    match self.get_mut() {
+      Connection::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+      #[cfg(feature = "tls")]
+      Connection::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+    }
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+      Connection::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+      #[cfg(feature = "tls")]
+      Connection::Tls(stream) => Pin::new(stream).poll_flush(cx),
+    }
+  }
+
+  fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    match self.get_mut() {
+      Connection::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+      #[cfg(feature = "tls")]
+      Connection::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+    }
+  }
+}
@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use crate::request_init::Method;
+use crate::streams::ReadableStream;
+use crate::url::URL;
+use super::FetchError;
+
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+const READ_SIZE: usize = 8 * 1024;
+
+/// 响应的状态行和头部
+pub(crate) struct ResponseHead {
+  pub status: u16,
+  pub status_text: String,
+  pub headers: HashMap<String, String>,
+  /// 服务器是否允许在这个响应之后继续使用连接
+  pub keep_alive: bool,
+}
+
+/// 生成请求行和请求头
+pub(crate) fn encode_request_head(method: &str, url: &URL, headers: &[String]) -> Vec<u8> {
+  let mut head = format!("{} {} HTTP/1.1\r\n", method, request_target(url));
+  for header in headers {
+    head.push_str(header);
+    head.push_str("\r\n");
+  }
+  head.push_str("\r\n");
+  head.into_bytes()
+}
+
+/// 请求行中的路径和查询；空格、控制字符和非ASCII字符按WHATWG的query percent-encode set编码，
+/// 避免来自`Location`等处的URL拆分请求行或注入请求头
+pub(crate) fn request_target(url: &URL) -> String {
+  let mut target = String::new();
+  for b in format!("{}{}", url.get_pathname(), url.get_search()).bytes() {
+    match b {
+      0..=0x20 | 0x7F.. | b'"' | b'#' | b'<' | b'>' => target.push_str(&format!("%{:02X}", b)),
+      _ => target.push(b as char),
+    }
+  }
+  target
+}
+
+/// 读取响应头，跳过`100 Continue`等中间响应，读取过程中多出的正文字节留在`buffer`中
+pub(crate) async fn read_response_head<R>(reader: &mut R, buffer: &mut Vec<u8>) -> Result<ResponseHead, FetchError>
+where
+  R: AsyncRead + Unpin,
+{
+  loop {
+    let head = read_head(reader, buffer).await?;
+    if head.status >= 200 || head.status == 101 {
+      return Ok(head);
+    }
+  }
+}
+
+async fn read_head<R>(reader: &mut R, buffer: &mut Vec<u8>) -> Result<ResponseHead, FetchError>
+where
+  R: AsyncRead + Unpin,
+{
+  let mut chunk = [0; 1024];
+  let end = loop {
+    if let Some(i) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+      break i;
+    }
+    if buffer.len() > MAX_HEAD_SIZE {
+      return Err(FetchError::InvalidResponse("response head too large".to_string()));
+    }
+    let n = reader.read(&mut chunk).await?;
+    if n == 0 {
+      return Err(FetchError::InvalidResponse("connection closed before response head".to_string()));
+    }
+    buffer.extend_from_slice(&chunk[..n]);
+  };
+  let head = String::from_utf8_lossy(&buffer[..end]).to_string();
+  buffer.drain(..end + 4);
+  parse_response_head(&head)
+}
+
+fn parse_response_head(head: &str) -> Result<ResponseHead, FetchError> {
+  let mut lines = head.split("\r\n");
+  let status_line = lines.next().unwrap_or_default();
+  let mut parts = status_line.splitn(3, ' ');
+  let version = parts.next().unwrap_or_default();
+  if !version.starts_with("HTTP/1.") {
+    return Err(FetchError::InvalidResponse(format!("invalid status line: {}", status_line)));
+  }
+  let status = parts.next()
+    .and_then(|s| s.parse::<u16>().ok())
+    .filter(|s| (100..1000).contains(s))
+    .ok_or_else(|| FetchError::InvalidResponse(format!("invalid status line: {}", status_line)))?;
+  let status_text = parts.next().unwrap_or_default().to_string();
+  let mut headers: HashMap<String, String> = HashMap::new();
+  for line in lines {
+    let (name, value) = line.split_once(':')
+      .ok_or_else(|| FetchError::InvalidResponse(format!("invalid header line: {}", line)))?;
+    let name = name.trim().to_ascii_lowercase();
+    let value = value.trim();
+    headers.entry(name)
+      .and_modify(|v| {
+        v.push_str(", ");
+        v.push_str(value);
+      })
+      .or_insert_with(|| value.to_string());
+  }
+  // HTTP/1.1默认保持连接，HTTP/1.0需要显式的keep-alive
+  let connection = headers.get("connection").map(|value| value.split(',').map(str::trim).collect::<Vec<_>>());
+  let has_option = |option: &str| connection.as_ref().is_some_and(|c| c.iter().any(|o| o.eq_ignore_ascii_case(option)));
+  // 同时带有Transfer-Encoding和Content-Length的响应可能是请求走私，按RFC 9112第6.3节读完后关闭连接
+  let smuggling = headers.contains_key("transfer-encoding") && headers.contains_key("content-length");
+  let keep_alive = status != 101 && !smuggling && match version {
+    "HTTP/1.0" => has_option("keep-alive"),
+    _ => !has_option("close"),
+  };
+  Ok(ResponseHead {
+    status,
+    status_text,
+    headers,
+    keep_alive,
+  })
+}
+
+/// 响应正文的分帧方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BodyFraming {
+  Empty,
+  Length(u64),
+  Chunked,
+  Close,
+}
+
+impl BodyFraming {
+  /// 按照RFC 9112第6.3节根据请求方法、状态码和响应头确定正文长度
+  pub(crate) fn of_response(method: &Method, head: &ResponseHead) -> Result<Self, FetchError> {
+    if *method == Method::HEAD || head.status < 200 || head.status == 204 || head.status == 304 {
+      return Ok(BodyFraming::Empty);
+    }
+    if let Some(encoding) = head.headers.get("transfer-encoding") {
+      let chunked = encoding.rsplit(',').next()
+        .map(|last| last.trim().eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false);
+      return Ok(if chunked { BodyFraming::Chunked } else { BodyFraming::Close });
+    }
+    if let Some(length) = head.headers.get("content-length") {
+      let mut lengths = length.split(',').map(|l| l.trim());
+      let first = lengths.next().unwrap_or_default();
+      if lengths.any(|l| l != first) {
+        return Err(FetchError::InvalidResponse(format!("conflicting content-length: {}", length)));
+      }
+      let length = first.parse::<u64>().ok()
+        .filter(|_| first.bytes().all(|b| b.is_ascii_digit()))
+        .ok_or_else(|| FetchError::InvalidResponse(format!("invalid content-length: {}", length)))?;
+      return Ok(if length == 0 { BodyFraming::Empty } else { BodyFraming::Length(length) });
+    }
+    Ok(BodyFraming::Close)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DecoderState {
+  Length(u64),
+  ChunkSize,
+  ChunkData(u64),
+  ChunkEnd,
+  Trailers,
+  Close,
+  Done,
+}
+
+/// HTTP/1.1响应正文解码器，支持Content-Length、chunked和读取到连接关闭
+pub(crate) struct BodyDecoder<R> {
+  reader: R,
+  buffer: Vec<u8>,
+  state: DecoderState,
+  /// 读到连接关闭为止的正文结束后连接就不能再使用
+  reusable: bool,
+  trailers: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl<R> BodyDecoder<R>
+where
+  R: AsyncRead + Unpin,
+{
+  /// `buffer`为读取响应头时多读出的字节
+  pub(crate) fn new(reader: R, buffer: Vec<u8>, framing: BodyFraming) -> Self {
+    let state = match framing {
+      BodyFraming::Empty => DecoderState::Done,
+      BodyFraming::Length(length) => DecoderState::Length(length),
+      BodyFraming::Chunked => DecoderState::ChunkSize,
+      BodyFraming::Close => DecoderState::Close,
+    };
+    Self {
+      reader,
+      buffer,
+      state,
+      reusable: framing != BodyFraming::Close,
+      trailers: Arc::new(Mutex::new(HashMap::new())),
+    }
+  }
+
+  /// chunked正文结尾的trailer字段，正文读完后才会被填充
+  pub(crate) fn trailers(&self) -> Arc<Mutex<HashMap<String, String>>> {
+    self.trailers.clone()
+  }
+
+  /// 读取下一段正文，正文结束时返回`None`
+  pub(crate) async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, FetchError> {
+    loop {
+      match self.state {
+        DecoderState::Done => return Ok(None),
+        DecoderState::Length(remaining) => {
+          let chunk = self.take(remaining).await?;
+          let remaining = remaining - chunk.len() as u64;
+          self.state = if remaining == 0 { DecoderState::Done } else { DecoderState::Length(remaining) };
+          return Ok(Some(chunk));
+        },
+        DecoderState::Close => {
+          if self.buffer.is_empty() && self.fill().await? == 0 {
+            self.state = DecoderState::Done;
+            return Ok(None);
+          }
+          return Ok(Some(std::mem::take(&mut self.buffer)));
+        },
+        DecoderState::ChunkSize => {
+          let line = self.read_line().await?;
+          let size = line.split(';').next().unwrap_or_default().trim();
+          // from_str_radix接受前导的`+`，这里只允许十六进制数字
+          let size = u64::from_str_radix(size, 16).ok()
+            .filter(|_| size.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| FetchError::InvalidResponse(format!("invalid chunk size: {}", line)))?;
+          self.state = if size == 0 { DecoderState::Trailers } else { DecoderState::ChunkData(size) };
+        },
+        DecoderState::ChunkData(remaining) => {
+          let chunk = self.take(remaining).await?;
+          let remaining = remaining - chunk.len() as u64;
+          self.state = if remaining == 0 { DecoderState::ChunkEnd } else { DecoderState::ChunkData(remaining) };
+          return Ok(Some(chunk));
+        },
+        DecoderState::ChunkEnd => {
+          let line = self.read_line().await?;
+          if !line.is_empty() {
+            return Err(FetchError::InvalidResponse("missing CRLF after chunk data".to_string()));
+          }
+          self.state = DecoderState::ChunkSize;
+        },
+        DecoderState::Trailers => {
+          let line = self.read_line().await?;
+          if line.is_empty() {
+            self.state = DecoderState::Done;
+            continue;
+          }
+          if let Some((name, value)) = line.split_once(':') {
+            self.trailers.lock().unwrap().insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+          }
+        },
+      }
+    }
+  }
+
+  /// 正文已读完且没有多余的字节时取回连接
+  pub(crate) fn into_reusable(self) -> Option<R> {
+    match self.state {
+      DecoderState::Done if self.reusable && self.buffer.is_empty() => Some(self.reader),
+      _ => None,
+    }
+  }
+
+  async fn fill(&mut self) -> Result<usize, FetchError> {
+    let mut chunk = [0; READ_SIZE];
+    let n = self.reader.read(&mut chunk).await?;
+    self.buffer.extend_from_slice(&chunk[..n]);
+    Ok(n)
+  }
+
+  /// 从缓冲区取出最多`limit`个字节，缓冲区为空时先从连接读取
+  async fn take(&mut self, limit: u64) -> Result<Vec<u8>, FetchError> {
+    if self.buffer.is_empty() && self.fill().await? == 0 {
+      return Err(FetchError::InvalidResponse("connection closed before end of body".to_string()));
+    }
+    let n = self.buffer.len().min(limit.min(usize::MAX as u64) as usize);
+    Ok(self.buffer.drain(..n).collect())
+  }
+
+  async fn read_line(&mut self) -> Result<String, FetchError> {
+    loop {
+      if let Some(i) = self.buffer.windows(2).position(|w| w == b"\r\n") {
+        let line = String::from_utf8_lossy(&self.buffer[..i]).to_string();
+        self.buffer.drain(..i + 2);
+        return Ok(line);
+      }
+      if self.buffer.len() > MAX_HEAD_SIZE {
+        return Err(FetchError::InvalidResponse("chunk line too long".to_string()));
+      }
+      if self.fill().await? == 0 {
+        return Err(FetchError::InvalidResponse("connection closed before end of body".to_string()));
+      }
+    }
+  }
+}
+
+impl<R> BodyDecoder<R>
+where
+  R: AsyncRead + Unpin + Send + 'static,
+{
+  /// 转换为按需从连接读取的字节流，正文读完后连接可以继续使用时交给`release`
+  pub(crate) fn into_stream(self, release: impl FnOnce(R) + Send + 'static) -> ReadableStream<Bytes> {
+    ReadableStream::from_stream(futures_util::stream::unfold(Some((self, release)), |state| async move {
+      let (mut decoder, release) = state?;
+      match decoder.next_chunk().await {
+        Ok(Some(chunk)) => Some((Ok(Bytes::from(chunk)), Some((decoder, release)))),
+        Ok(None) => {
+          if let Some(reader) = decoder.into_reusable() {
+            release(reader);
+          }
+          None
+        },
+        Err(e) => Some((Err(e.into()), None)),
+      }
+    }))
+  }
+}
+
+/// 将一段数据编码为chunked格式，用于长度未知的流式请求正文
+pub(crate) fn encode_chunk(data: &[u8]) -> Vec<u8> {
+  let mut chunk = format!("{:x}\r\n", data.len()).into_bytes();
+  chunk.extend_from_slice(data);
+  chunk.extend_from_slice(b"\r\n");
+  chunk
+}
+
+/// chunked正文的结束块
+pub(crate) const LAST_CHUNK: &[u8] = b"0\r\n\r\n";
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn encode_request_target() {
+    let url = URL::new("http://example.com/a b\r\nX-Injected/你?q=\"<x>\"\t");
+    let head = String::from_utf8(encode_request_head("GET", &url, &[])).unwrap();
+    let line = head.split("\r\n").next().unwrap();
+    assert!(line.starts_with("GET /a%20b%0D%0AX-Injected/%E4%BD%A0?q=%22%3Cx%3E%22") && line.ends_with(" HTTP/1.1"), "{}", line);
+    assert!(!line[4..line.len() - 9].bytes().any(|b| b <= 0x20 || b >= 0x7F || b == b'"'), "{}", line);
+    assert_eq!(head.matches("\r\n").count(), 2);
+    assert_eq!(request_target(&URL::new("http://example.com/a%20b?x=%22")), "/a%20b?x=%22");
+  }
+
+  #[test]
+  fn parse_head() {
+    let head = parse_response_head("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nSet-Cookie: a=1\r\nset-cookie: b=2").unwrap();
+    assert_eq!(head.status, 200);
+    assert_eq!(head.status_text, "OK");
+    assert_eq!(head.headers.get("content-type").unwrap(), "text/plain");
+    assert_eq!(head.headers.get("set-cookie").unwrap(), "a=1, b=2");
+    assert!(head.keep_alive);
+  }
+
+  #[test]
+  fn keep_alive() {
+    let keep_alive = |head: &str| parse_response_head(head).unwrap().keep_alive;
+    assert!(!keep_alive("HTTP/1.1 200 OK\r\nConnection: Upgrade, Close"));
+    assert!(!keep_alive("HTTP/1.0 200 OK"));
+    assert!(keep_alive("HTTP/1.0 200 OK\r\nConnection: keep-alive"));
+    assert!(!keep_alive("HTTP/1.1 101 Switching Protocols\r\nConnection: upgrade"));
+    assert!(!keep_alive("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 5"));
+    assert!(keep_alive("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked"));
+  }
+
+  #[test]
+  fn reject_invalid_status_line() {
+    assert!(parse_response_head("SSH-2.0-OpenSSH").is_err());
+    assert!(parse_response_head("HTTP/1.1 abc OK").is_err());
+  }
+
+  async fn read_to_end<R: AsyncRead + Unpin>(decoder: &mut BodyDecoder<R>) -> Result<Vec<u8>, FetchError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = decoder.next_chunk().await? {
+      body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+  }
+
+  async fn decode(framing: BodyFraming, input: &'static [u8]) -> Result<Vec<u8>, FetchError> {
+    read_to_end(&mut BodyDecoder::new(input, Vec::new(), framing)).await
+  }
+
+  #[tokio::test]
+  async fn decode_content_length() {
+    let mut decoder = BodyDecoder::new(&b"lo world, next response"[..], b"hel".to_vec(), BodyFraming::Length(11));
+    assert_eq!(read_to_end(&mut decoder).await.unwrap(), b"hello world");
+    assert_eq!(decoder.buffer, b", next response");
+    assert!(decoder.into_reusable().is_none());
+    assert!(decode(BodyFraming::Length(10), b"short").await.is_err());
+  }
+
+  #[tokio::test]
+  async fn decode_chunked() {
+    let input = b"5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nExpires: never\r\n\r\nHTTP/1.1";
+    let mut decoder = BodyDecoder::new(&input[..], Vec::new(), BodyFraming::Chunked);
+    assert_eq!(read_to_end(&mut decoder).await.unwrap(), b"hello world");
+    assert_eq!(decoder.trailers().lock().unwrap().get("expires").unwrap(), "never");
+    assert!(decode(BodyFraming::Chunked, b"zz\r\n").await.is_err());
+    assert!(decode(BodyFraming::Chunked, b"+5\r\nhello\r\n0\r\n\r\n").await.is_err());
+    assert!(decode(BodyFraming::Chunked, b"-0\r\n\r\n").await.is_err());
+    assert!(decode(BodyFraming::Chunked, b"5\r\nhel").await.is_err());
+  }
+
+  #[tokio::test]
+  async fn decode_until_close() {
+    let mut decoder = BodyDecoder::new(&b"all of it"[..], Vec::new(), BodyFraming::Close);
+    assert_eq!(read_to_end(&mut decoder).await.unwrap(), b"all of it");
+    assert!(decoder.into_reusable().is_none());
+  }
+
+  #[test]
+  fn framing() {
+    let head = parse_response_head("HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, chunked\r\nContent-Length: 5").unwrap();
+    assert_eq!(BodyFraming::of_response(&Method::GET, &head).unwrap(), BodyFraming::Chunked);
+    assert_eq!(BodyFraming::of_response(&Method::HEAD, &head).unwrap(), BodyFraming::Empty);
+    let head = parse_response_head("HTTP/1.1 304 Not Modified\r\nContent-Length: 5").unwrap();
+    assert_eq!(BodyFraming::of_response(&Method::GET, &head).unwrap(), BodyFraming::Empty);
+    let head = parse_response_head("HTTP/1.1 200 OK\r\nContent-Length: 5, 6").unwrap();
+    assert!(BodyFraming::of_response(&Method::GET, &head).is_err());
+    let head = parse_response_head("HTTP/1.1 200 OK\r\nContent-Length: +5").unwrap();
+    assert!(BodyFraming::of_response(&Method::GET, &head).is_err());
+  }
+
+  #[test]
+  fn chunk_encoding() {
+    assert_eq!(encode_chunk(b"hello world, again"), b"12\r\nhello world, again\r\n");
+  }
+}
@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use h2::client::SendRequest;
+use h2::{Reason, RecvStream, SendStream};
+use tokio::io::{AsyncRead, AsyncWrite};
+use crate::request_init::{BodyInit, Method};
+use crate::streams::ReadableStream;
+use crate::url::URL;
+use super::http1::ResponseHead;
+use super::pool::{ConnectionPool, PoolOptions};
+use super::{FetchError, Response};
+
+/// HTTP/2中禁止出现的逐跳请求头，`Host`由`:authority`代替
+const CONNECTION_HEADERS: [&str; 6] = ["connection", "host", "keep-alive", "proxy-connection", "transfer-encoding", "upgrade"];
+
+/// HTTP/2连接的设置，作为`PoolOptions`的一部分对连接池中的所有HTTP/2连接生效
+///
+/// `https:`连接总是通过ALPN协商HTTP/2，服务器不支持时使用HTTP/1.1
+///
+/// # Example
+/// ```
+/// use fetch_js::{ConnectionPool, Http2Options, PoolOptions};
+///
+/// let pool = ConnectionPool::new(PoolOptions {
+///   http2: Http2Options {
+///     prior_knowledge: true,
+///     initial_stream_window_size: 1024 * 1024,
+///     ..Default::default()
+///   },
+///   ..Default::default()
+/// });
+/// assert!(pool.options().http2.prior_knowledge);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Http2Options {
+  /// `http:`不经过协商直接使用HTTP/2（h2c），服务器必须支持
+  pub prior_knowledge: bool,
+  /// 每个流的初始接收窗口（SETTINGS_INITIAL_WINDOW_SIZE）
+  pub initial_stream_window_size: u32,
+  /// 整个连接的接收窗口
+  pub initial_connection_window_size: u32,
+  /// 允许接收的最大帧（SETTINGS_MAX_FRAME_SIZE），限制在16KiB到16MiB-1之间
+  pub max_frame_size: u32,
+  /// 允许接收的响应头总大小（SETTINGS_MAX_HEADER_LIST_SIZE）
+  pub max_header_list_size: u32,
+}
+
+impl Default for Http2Options {
+  fn default() -> Self {
+    Self {
+      prior_knowledge: false,
+      initial_stream_window_size: 2 * 1024 * 1024,
+      initial_connection_window_size: 5 * 1024 * 1024,
+      max_frame_size: 16 * 1024,
+      max_header_list_size: 64 * 1024,
+    }
+  }
+}
+
+/// HTTP/2连接的发送端，克隆后可以在同一个连接上同时发送多个请求
+pub(crate) type Sender = SendRequest<Bytes>;
+
+/// 在已建立的连接上进行HTTP/2握手并放入连接池，返回发送端和它在连接池中的编号
+///
+/// 连接由后台任务驱动，连接关闭、距上次分配请求超过空闲时间或超过寿命后从连接池中移除
+pub(crate) async fn handshake<T>(
+  io: T,
+  options: PoolOptions,
+  pool: ConnectionPool,
+  key: String,
+) -> Result<(Sender, u64), FetchError>
+where
+  T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+  let settings = options.http2;
+  let (sender, connection) = h2::client::Builder::new()
+    .initial_window_size(settings.initial_stream_window_size)
+    .initial_connection_window_size(settings.initial_connection_window_size)
+    .max_frame_size(settings.max_frame_size.clamp(16 * 1024, 16 * 1024 * 1024 - 1))
+    .max_header_list_size(settings.max_header_list_size)
+    .enable_push(false)
+    .handshake::<T, Bytes>(io)
+    .await?;
+  let last_used = Arc::new(Mutex::new(Instant::now()));
+  let id = pool.insert_http2(&key, sender.clone(), last_used.clone());
+  let created = Instant::now();
+  tokio::spawn(async move {
+    tokio::pin!(connection);
+    loop {
+      let idle_deadline = *last_used.lock().unwrap() + options.idle_timeout;
+      let deadline = options.max_lifetime.map_or(idle_deadline, |lifetime| idle_deadline.min(created + lifetime));
+      tokio::select! {
+        _ = &mut connection => {
+          pool.remove_http2(&key, id);
+          return;
+        },
+        // 期间有新的请求时空闲的截止时间会推后
+        _ = tokio::time::sleep_until(deadline.into()) => {
+          let now = Instant::now();
+          if now >= *last_used.lock().unwrap() + options.idle_timeout
+            || options.max_lifetime.is_some_and(|lifetime| now >= created + lifetime) {
+            break;
+          }
+        },
+      }
+    }
+    // 不再分配新的请求，已有的流结束后连接自然关闭
+    pool.remove_http2(&key, id);
+    let _ = connection.await;
+  });
+  Ok((sender, id))
+}
+
+/// 在HTTP/2连接上发送请求并读取响应头
+///
+/// 服务器没有处理请求就拒绝了它时返回`None`，这类请求可以重试，见`is_refused`
+pub(crate) async fn send(
+  sender: Sender,
+  url: &URL,
+  method: &Method,
+  headers: &HashMap<String, String>,
+  body: Option<BodyInit>,
+) -> Result<Option<Response>, FetchError> {
+  let request = build_request(url, method, headers)?;
+  // 请求还没有发出，服务器发送GOAWAY后可以在新的连接上重试
+  let mut sender = match sender.ready().await {
+    Ok(sender) => sender,
+    Err(e) if e.is_go_away() && e.is_remote() => return Ok(None),
+    Err(e) => return Err(e.into()),
+  };
+  let (response, mut stream) = match sender.send_request(request, body.is_none()) {
+    Ok(sent) => sent,
+    Err(e) if e.is_go_away() && e.is_remote() => return Ok(None),
+    Err(e) => return Err(e.into()),
+  };
+  if let Some(body) = body {
+    write_body(&mut stream, body).await?;
+  }
+  let response = match response.await {
+    Ok(response) => response,
+    Err(e) if is_refused(&e, method) => return Ok(None),
+    Err(e) => return Err(e.into()),
+  };
+  let (parts, body) = response.into_parts();
+  let head = ResponseHead {
+    status: parts.status.as_u16(),
+    // HTTP/2没有原因短语
+    status_text: String::new(),
+    headers: header_map(&parts.headers),
+    keep_alive: true,
+  };
+  let trailers = Arc::default();
+  Ok(Some(Response::new(head, url.clone(), into_stream(body, Arc::clone(&trailers)), trailers)))
+}
+
+/// 已发出的请求是否可以重试：`REFUSED_STREAM`和`NO_ERROR`的GOAWAY表示服务器没有处理这个流；
+/// 带错误码的GOAWAY也会让已经处理的流失败，只重试幂等的请求
+fn is_refused(e: &h2::Error, method: &Method) -> bool {
+  match e.reason() {
+    Some(Reason::REFUSED_STREAM) => true,
+    Some(reason) if e.is_go_away() && e.is_remote() => reason == Reason::NO_ERROR || method.is_idempotent(),
+    _ => false,
+  }
+}
+
+fn build_request(url: &URL, method: &Method, headers: &HashMap<String, String>) -> Result<http::Request<()>, FetchError> {
+  let uri = format!("{}//{}{}", url.get_protocol(), url.get_host(), super::http1::request_target(url));
+  let mut request = http::Request::builder()
+    .method(method.as_str())
+    .uri(uri)
+    .body(())
+    .map_err(|e| FetchError::InvalidRequest(e.to_string()))?;
+  for (name, value) in headers {
+    let lowercase = name.to_ascii_lowercase();
+    if CONNECTION_HEADERS.contains(&lowercase.as_str()) || (lowercase == "te" && !value.eq_ignore_ascii_case("trailers")) {
+      continue;
+    }
+    let name = http::HeaderName::from_bytes(lowercase.as_bytes())
+      .map_err(|_| FetchError::InvalidRequest(format!("invalid header name: {}", name)))?;
+    let value = http::HeaderValue::from_str(value)
+      .map_err(|_| FetchError::InvalidRequest(format!("invalid header value for {}", name)))?;
+    request.headers_mut().append(name, value);
+  }
+  Ok(request)
+}
+
+/// 按流量控制窗口分段发送请求正文
+async fn write_body(stream: &mut SendStream<Bytes>, body: BodyInit) -> Result<(), FetchError> {
+  let length = body.len();
+  let mut chunks = body.into_stream();
+  let mut written = 0;
+  while let Some(chunk) = chunks.next().await {
+    let mut chunk = chunk?;
+    written += chunk.len() as u64;
+    if length.is_some_and(|length| written > length) {
+      break;
+    }
+    while !chunk.is_empty() {
+      stream.reserve_capacity(chunk.len());
+      if stream.capacity() == 0 {
+        match poll_fn(|cx| stream.poll_capacity(cx)).await {
+          Some(capacity) => capacity?,
+          None => return Err(FetchError::Network("stream closed while sending the request body".to_string())),
+        };
+        continue;
+      }
+      let data = chunk.split_to(stream.capacity().min(chunk.len()));
+      stream.send_data(data, false)?;
+    }
+  }
+  if let Some(length) = length.filter(|length| written != *length) {
+    stream.send_reset(Reason::CANCEL);
+    return Err(FetchError::InvalidRequest(format!("body length {} does not match Content-Length {}", written, length)));
+  }
+  Ok(stream.send_data(Bytes::new(), true)?)
+}
+
+/// 转换为字节流，读取后释放接收窗口，流结束时读取trailer字段
+fn into_stream(body: RecvStream, trailers: Arc<Mutex<HashMap<String, String>>>) -> ReadableStream<Bytes> {
+  ReadableStream::from_stream(futures_util::stream::unfold(Some(body), move |body| {
+    let trailers = trailers.clone();
+    async move {
+      let mut body = body?;
+      match body.data().await {
+        Some(Ok(chunk)) => {
+          let _ = body.flow_control().release_capacity(chunk.len());
+          Some((Ok(chunk), Some(body)))
+        },
+        Some(Err(e)) => Some((Err(FetchError::from(e).into()), None)),
+        None => match body.trailers().await {
+          Ok(fields) => {
+            trailers.lock().unwrap().extend(fields.as_ref().map(header_map).unwrap_or_default());
+            None
+          },
+          Err(e) => Some((Err(FetchError::from(e).into()), None)),
+        },
+      }
+    }
+  }))
+}
+
+/// 转换为与HTTP/1.1响应相同的形式，同名字段以`, `连接
+fn header_map(headers: &http::HeaderMap) -> HashMap<String, String> {
+  let mut map: HashMap<String, String> = HashMap::new();
+  for (name, value) in headers {
+    let value = String::from_utf8_lossy(value.as_bytes());
+    map.entry(name.as_str().to_string())
+      .and_modify(|v| {
+        v.push_str(", ");
+        v.push_str(&value);
+      })
+      .or_insert_with(|| value.to_string());
+  }
+  map
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use h2::server::SendResponse;
+  use tokio::net::TcpListener;
+  use crate::request_init::RequestInit;
+
+  /// 处理一个HTTP/2连接，`/echo`原样返回请求正文并在trailer中给出长度，其余请求返回连接的序号；
+  /// 处理`limit`个请求后发送GOAWAY
+  pub(crate) async fn serve_connection<T>(io: T, index: usize, limit: usize)
+  where
+    T: AsyncRead + AsyncWrite + Unpin,
+  {
+    let mut connection = h2::server::handshake(io).await.unwrap();
+    let mut handled = 0;
+    while let Some(request) = connection.accept().await {
+      let Ok((request, respond)) = request else { return };
+      tokio::spawn(respond_to(request, respond, index));
+      handled += 1;
+      if handled == limit {
+        connection.graceful_shutdown();
+      }
+    }
+  }
+
+  async fn respond_to(request: http::Request<RecvStream>, mut respond: SendResponse<Bytes>, index: usize) {
+    let (parts, mut body) = request.into_parts();
+    let mut received = Vec::new();
+    while let Some(chunk) = body.data().await {
+      let chunk = chunk.unwrap();
+      let _ = body.flow_control().release_capacity(chunk.len());
+      received.extend_from_slice(&chunk);
+    }
+    let mut response = http::Response::builder().header("x-method", parts.method.as_str());
+    for (name, value) in parts.headers.iter().filter(|(name, _)| name.as_str().starts_with("x-")) {
+      response = response.header(name, value);
+    }
+    let mut stream = respond.send_response(response.body(()).unwrap(), false).unwrap();
+    if parts.uri.path() != "/echo" {
+      stream.send_data(Bytes::from(format!("connection {}", index)), true).unwrap();
+      return;
+    }
+    let length = received.len();
+    stream.send_data(Bytes::from(received), false).unwrap();
+    let mut trailers = http::HeaderMap::new();
+    trailers.insert("x-length", length.into());
+    stream.send_trailers(trailers).unwrap();
+  }
+
+  /// 启动一个h2c服务器，返回地址和已接受的连接数
+  async fn serve_h2c(limit: usize) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    tokio::spawn(async move {
+      loop {
+        let (socket, _) = listener.accept().await.unwrap();
+        let index = accepted.fetch_add(1, Ordering::SeqCst);
+        tokio::spawn(serve_connection(socket, index, limit));
+      }
+    });
+    (addr, connections)
+  }
+
+  fn prior_knowledge() -> ConnectionPool {
+    ConnectionPool::new(PoolOptions {
+      http2: Http2Options { prior_knowledge: true, ..Default::default() },
+      ..Default::default()
+    })
+  }
+
+  #[tokio::test]
+  async fn multiplexing() {
+    let (addr, connections) = serve_h2c(usize::MAX).await;
+    let url = URL::new(&format!("http://{}/", addr));
+    let pool = prior_knowledge();
+    let responses = futures_util::future::join_all((0..5).map(|_| {
+      let init = RequestInit::builder().pool(pool.clone()).build().unwrap();
+      crate::fetch(url.clone(), init)
+    })).await;
+    for response in responses {
+      let mut response = response.unwrap();
+      assert_eq!(response.status(), 200);
+      assert_eq!(response.status_text(), "");
+      assert_eq!(response.text().await.unwrap(), "connection 0");
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+    assert_eq!(pool.stats().created, 1);
+    assert_eq!(pool.stats().reused, 4);
+  }
+
+  #[tokio::test]
+  async fn headers_trailers_and_flow_control() {
+    let (addr, _) = serve_h2c(usize::MAX).await;
+    // 超过默认的64KiB窗口，需要等待服务器释放窗口才能发送完
+    let body = vec![b'a'; 300 * 1024];
+    let init = RequestInit::builder()
+      .method(Method::POST)
+      .header("X-Request", "hello")
+      .header("TE", "gzip")
+      .body(body.clone())
+      .pool(prior_knowledge())
+      .build()
+      .unwrap();
+    let mut response = crate::fetch(URL::new(&format!("http://{}/echo", addr)), init).await.unwrap();
+    assert_eq!(response.headers().get("x-method").map(String::as_str), Some("POST"));
+    assert_eq!(response.headers().get("x-request").map(String::as_str), Some("hello"));
+    assert_eq!(response.bytes().await.unwrap(), body);
+    assert_eq!(response.trailers().get("x-length"), Some(&body.len().to_string()));
+  }
+
+  #[tokio::test]
+  async fn reconnect_after_goaway() {
+    let (addr, connections) = serve_h2c(2).await;
+    let url = URL::new(&format!("http://{}/", addr));
+    let pool = prior_knowledge();
+    let text = || {
+      let init = RequestInit::builder().pool(pool.clone()).build().unwrap();
+      let url = url.clone();
+      async move { crate::fetch(url, init).await.unwrap().text().await.unwrap() }
+    };
+    assert_eq!(text().await, "connection 0");
+    assert_eq!(text().await, "connection 0");
+    assert_eq!(text().await, "connection 1");
+    assert_eq!(text().await, "connection 1");
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+  }
+
+  /// 第一个连接收到请求后以`INTERNAL_ERROR`发送GOAWAY并关闭，之后的连接正常处理请求
+  async fn serve_aborting() -> (String, Arc<AtomicUsize>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    tokio::spawn(async move {
+      loop {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let index = accepted.fetch_add(1, Ordering::SeqCst);
+        if index > 0 {
+          tokio::spawn(serve_connection(socket, index, usize::MAX));
+          continue;
+        }
+        tokio::spawn(async move {
+          // 等客户端发出请求，再发送SETTINGS和last_stream_id为0的GOAWAY
+          tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+          let _ = socket.read(&mut [0; 4096]).await;
+          let settings = [0, 0, 0, 4, 0, 0, 0, 0, 0];
+          let go_away = [0, 0, 8, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+          socket.write_all(&[settings.as_slice(), go_away.as_slice()].concat()).await.unwrap();
+          // 等客户端读到GOAWAY后再关闭连接
+          tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        });
+      }
+    });
+    (addr, connections)
+  }
+
+  #[tokio::test]
+  async fn replay_only_idempotent_after_goaway_error() {
+    let (addr, connections) = serve_aborting().await;
+    let init = RequestInit::builder().method(Method::POST).body("a").pool(prior_knowledge()).build().unwrap();
+    let err = crate::fetch(URL::new(&format!("http://{}/", addr)), init).await.err().unwrap();
+    assert!(matches!(err, FetchError::Http2(_)), "{:?}", err);
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    let (addr, connections) = serve_aborting().await;
+    let init = RequestInit::builder().method(Method::PUT).body("a").pool(prior_knowledge()).build().unwrap();
+    let mut response = crate::fetch(URL::new(&format!("http://{}/", addr)), init).await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "connection 1");
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+  }
+
+  #[tokio::test]
+  async fn idle_connections_are_closed() {
+    let (addr, connections) = serve_h2c(usize::MAX).await;
+    let url = URL::new(&format!("http://{}/", addr));
+    let pool = prior_knowledge();
+    pool.set_options(PoolOptions { idle_timeout: std::time::Duration::from_millis(50), ..pool.options() });
+    let init = || RequestInit::builder().pool(pool.clone()).build().unwrap();
+    assert_eq!(crate::fetch(url.clone(), init()).await.unwrap().text().await.unwrap(), "connection 0");
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert_eq!(crate::fetch(url.clone(), init()).await.unwrap().text().await.unwrap(), "connection 1");
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+  }
+}
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, ReadBuf};
+use super::connection::Connection;
+
+/// 连接池的限制
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use fetch_js::{ConnectionPool, PoolOptions};
+///
+/// let pool = ConnectionPool::new(PoolOptions {
+///   max_idle_per_host: 4,
+///   max_lifetime: Some(Duration::from_secs(300)),
+///   ..Default::default()
+/// });
+/// assert_eq!(pool.options().idle_timeout, Duration::from_secs(90));
+/// assert_eq!(pool.stats().idle, 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolOptions {
+  /// 每个主机最多保留的空闲连接数，为0时不保留连接
+  pub max_idle_per_host: usize,
+  /// 空闲超过这个时间的连接会被关闭
+  pub idle_timeout: Duration,
+  /// 连接建立后最多使用的时间，到期后不再放回连接池
+  pub max_lifetime: Option<Duration>,
+  #[cfg(feature = "http2")]
+  pub http2: super::http2::Http2Options,
+}
+
+impl Default for PoolOptions {
+  fn default() -> Self {
+    Self {
+      max_idle_per_host: 32,
+      idle_timeout: Duration::from_secs(90),
+      max_lifetime: None,
+      #[cfg(feature = "http2")]
+      http2: Default::default(),
+    }
+  }
+}
+
+/// 连接池的统计数据，计数从连接池创建时开始累计
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PoolStats {
+  /// 当前空闲的连接数
+  pub idle: usize,
+  /// 新建的连接数
+  pub created: u64,
+  /// 复用空闲连接的次数
+  pub reused: u64,
+  /// 因空闲超时、超过寿命、健康检查失败或超出空闲数量上限被关闭的连接数
+  pub evicted: u64,
+}
+
+/// `keepalive`请求使用的HTTP/1.1连接池，按协议、主机、端口和TLS配置区分连接
+///
+/// 响应正文被完整读取且服务器没有要求关闭时，连接才会放回连接池；
+/// 取出空闲连接前会检查连接是否已被服务器关闭。克隆得到的是同一个连接池
+///
+/// 启用`http2`特性时，每个源最多保持一个HTTP/2连接，所有请求（不论是否`keepalive`）在其上多路复用
+#[derive(Clone, Default)]
+pub struct ConnectionPool {
+  inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+  options: Mutex<PoolOptions>,
+  idle: Mutex<HashMap<String, Vec<Idle>>>,
+  created: AtomicU64,
+  reused: AtomicU64,
+  evicted: AtomicU64,
+  #[cfg(feature = "http2")]
+  http2: Mutex<HashMap<String, Http2Entry>>,
+  /// 同一个源同时只建立一个可能是HTTP/2的连接，避免并发的请求各自建立连接；
+  /// 值为刚建立的连接是否使用HTTP/1.1
+  #[cfg(feature = "http2")]
+  connecting: Mutex<HashMap<String, Arc<tokio::sync::Mutex<bool>>>>,
+  #[cfg(feature = "http2")]
+  next_id: AtomicU64,
+}
+
+#[cfg(feature = "http2")]
+struct Http2Entry {
+  id: u64,
+  sender: super::http2::Sender,
+  last_used: Arc<Mutex<Instant>>,
+}
+
+struct Idle {
+  connection: Connection,
+  created: Instant,
+  since: Instant,
+}
+
+impl ConnectionPool {
+  pub fn new(options: PoolOptions) -> Self {
+    let pool = Self::default();
+    pool.set_options(options);
+    pool
+  }
+
+  /// 没有在`RequestInit`中指定连接池时使用的全局连接池
+  pub fn global() -> &'static ConnectionPool {
+    static POOL: OnceLock<ConnectionPool> = OnceLock::new();
+    POOL.get_or_init(ConnectionPool::default)
+  }
+
+  pub fn options(&self) -> PoolOptions {
+    *self.inner.options.lock().unwrap()
+  }
+
+  /// 修改限制，已有的空闲连接在下次取出或放回时按新的限制检查
+  pub fn set_options(&self, options: PoolOptions) {
+    *self.inner.options.lock().unwrap() = options;
+  }
+
+  pub fn stats(&self) -> PoolStats {
+    PoolStats {
+      idle: self.inner.idle.lock().unwrap().values().map(Vec::len).sum(),
+      created: self.inner.created.load(Ordering::Relaxed),
+      reused: self.inner.reused.load(Ordering::Relaxed),
+      evicted: self.inner.evicted.load(Ordering::Relaxed),
+    }
+  }
+
+  /// 关闭所有空闲连接，HTTP/2连接在已有的请求完成后关闭
+  pub fn clear(&self) {
+    let closed = std::mem::take(&mut *self.inner.idle.lock().unwrap());
+    self.evict(closed.values().map(Vec::len).sum());
+    #[cfg(feature = "http2")]
+    self.inner.http2.lock().unwrap().clear();
+  }
+
+  /// 取出最近放回的可用连接，同时关闭过期或已失效的空闲连接
+  pub(crate) fn checkout(&self, key: &str) -> Option<(Connection, Instant)> {
+    let options = self.options();
+    let now = Instant::now();
+    let mut idle = self.inner.idle.lock().unwrap();
+    let connections = idle.get_mut(key)?;
+    let mut evicted = 0;
+    let mut found = None;
+    while let Some(mut entry) = connections.pop() {
+      if entry.is_expired(&options, now) || !is_healthy(&mut entry.connection) {
+        evicted += 1;
+        continue;
+      }
+      found = Some((entry.connection, entry.created));
+      break;
+    }
+    if connections.is_empty() {
+      idle.remove(key);
+    }
+    drop(idle);
+    self.evict(evicted);
+    if found.is_some() {
+      self.inner.reused.fetch_add(1, Ordering::Relaxed);
+    }
+    found
+  }
+
+  /// 记录新建的连接
+  pub(crate) fn created(&self) {
+    self.inner.created.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// 放回可以复用的连接，超过寿命或空闲数量上限时直接关闭
+  pub(crate) fn release(&self, key: &str, connection: Connection, created: Instant) {
+    let options = self.options();
+    let now = Instant::now();
+    let entry = Idle { connection, created, since: now };
+    let mut idle = self.inner.idle.lock().unwrap();
+    let connections = idle.entry(key.to_string()).or_default();
+    let before = connections.len();
+    connections.retain(|idle| !idle.is_expired(&options, now));
+    let mut evicted = before - connections.len();
+    if connections.len() < options.max_idle_per_host && !entry.is_expired(&options, now) {
+      connections.push(entry);
+    } else {
+      evicted += 1;
+    }
+    if connections.is_empty() {
+      idle.remove(key);
+    }
+    drop(idle);
+    self.evict(evicted);
+  }
+
+  /// 取出这个源的HTTP/2连接
+  #[cfg(feature = "http2")]
+  pub(crate) fn http2(&self, key: &str) -> Option<(super::http2::Sender, u64)> {
+    let http2 = self.inner.http2.lock().unwrap();
+    let entry = http2.get(key)?;
+    *entry.last_used.lock().unwrap() = Instant::now();
+    self.inner.reused.fetch_add(1, Ordering::Relaxed);
+    Some((entry.sender.clone(), entry.id))
+  }
+
+  #[cfg(feature = "http2")]
+  pub(crate) fn insert_http2(&self, key: &str, sender: super::http2::Sender, last_used: Arc<Mutex<Instant>>) -> u64 {
+    let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+    self.inner.http2.lock().unwrap().insert(key.to_string(), Http2Entry { id, sender, last_used });
+    self.created();
+    id
+  }
+
+  /// 移除HTTP/2连接，`id`不同说明已经被新的连接替换
+  #[cfg(feature = "http2")]
+  pub(crate) fn remove_http2(&self, key: &str, id: u64) {
+    let mut http2 = self.inner.http2.lock().unwrap();
+    if http2.get(key).is_some_and(|entry| entry.id == id) {
+      http2.remove(key);
+    }
+  }
+
+  /// 等待同一个源上正在建立的连接完成；刚建立的连接使用HTTP/1.1时不必再等待，返回`None`
+  #[cfg(feature = "http2")]
+  pub(crate) async fn lock_connecting(&self, key: &str) -> Option<Connecting> {
+    let lock = self.inner.connecting.lock().unwrap().entry(key.to_string()).or_default().clone();
+    let connecting = Connecting { pool: self.clone(), key: key.to_string(), guard: lock.lock_owned().await };
+    (!*connecting.guard).then_some(connecting)
+  }
+
+  fn evict(&self, count: usize) {
+    self.inner.evicted.fetch_add(count as u64, Ordering::Relaxed);
+  }
+}
+
+/// `lock_connecting`得到的锁，释放时没有其他请求在等待就移除这个源的记录
+#[cfg(feature = "http2")]
+pub(crate) struct Connecting {
+  pool: ConnectionPool,
+  key: String,
+  guard: tokio::sync::OwnedMutexGuard<bool>,
+}
+
+#[cfg(feature = "http2")]
+impl Connecting {
+  /// 建立的连接使用HTTP/1.1，等待中的请求各自建立连接
+  pub(crate) fn http1(mut self) {
+    *self.guard = true;
+  }
+}
+
+#[cfg(feature = "http2")]
+impl Drop for Connecting {
+  fn drop(&mut self) {
+    let mut connecting = self.pool.inner.connecting.lock().unwrap();
+    // 只剩连接池和这个锁引用时没有其他请求在等待
+    if Arc::strong_count(tokio::sync::OwnedMutexGuard::mutex(&self.guard)) == 2 {
+      connecting.remove(&self.key);
+    }
+  }
+}
+
+impl std::fmt::Debug for ConnectionPool {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ConnectionPool")
+      .field("options", &self.options())
+      .field("stats", &self.stats())
+      .finish()
+  }
+}
+
+impl Idle {
+  fn is_expired(&self, options: &PoolOptions, now: Instant) -> bool {
+    now.duration_since(self.since) >= options.idle_timeout
+      || options.max_lifetime.is_some_and(|lifetime| now.duration_since(self.created) >= lifetime)
+  }
+}
+
+/// 空闲连接上不应有可读的数据，读到数据、连接关闭或出错都说明连接不能再使用
+fn is_healthy(connection: &mut Connection) -> bool {
+  let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+  let mut byte = [0; 1];
+  let mut buf = ReadBuf::new(&mut byte);
+  matches!(std::pin::Pin::new(connection).poll_read(&mut cx, &mut buf), Poll::Pending)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tokio::io::AsyncWriteExt;
+  use tokio::net::{TcpListener, TcpStream};
+
+  async fn connect(listener: &TcpListener) -> (Connection, TcpStream) {
+    let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    (Connection::Tcp(client), server)
+  }
+
+  #[tokio::test]
+  async fn checkout_and_release() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let pool = ConnectionPool::new(PoolOptions { max_idle_per_host: 1, ..Default::default() });
+    let (a, _a) = connect(&listener).await;
+    let (b, _b) = connect(&listener).await;
+    pool.release("http://a", a, Instant::now());
+    pool.release("http://a", b, Instant::now());
+    assert_eq!(pool.stats(), PoolStats { idle: 1, created: 0, reused: 0, evicted: 1 });
+    assert!(pool.checkout("http://b").is_none());
+    assert!(pool.checkout("http://a").is_some());
+    assert!(pool.checkout("http://a").is_none());
+    assert_eq!(pool.stats().reused, 1);
+  }
+
+  #[cfg(feature = "http2")]
+  #[tokio::test]
+  async fn connecting_lock() {
+    let pool = ConnectionPool::default();
+    let first = pool.lock_connecting("https://a").await.unwrap();
+    let waiter = tokio::spawn({
+      let pool = pool.clone();
+      async move { pool.lock_connecting("https://a").await.is_none() }
+    });
+    while Arc::strong_count(&pool.inner.connecting.lock().unwrap()["https://a"]) < 3 {
+      tokio::task::yield_now().await;
+    }
+    first.http1();
+    assert!(waiter.await.unwrap());
+    assert!(pool.inner.connecting.lock().unwrap().is_empty());
+    drop(pool.lock_connecting("https://a").await.unwrap());
+    assert!(pool.inner.connecting.lock().unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn evict_unusable_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let pool = ConnectionPool::new(PoolOptions {
+      max_lifetime: Some(Duration::from_secs(60)),
+      ..Default::default()
+    });
+    // 服务器关闭了连接
+    let (closed, server) = connect(&listener).await;
+    drop(server);
+    // 服务器发来了多余的数据
+    let (unexpected, mut server) = connect(&listener).await;
+    server.write_all(b"HTTP/1.1 408 Request Timeout\r\n\r\n").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    pool.release("http://a", closed, Instant::now());
+    pool.release("http://a", unexpected, Instant::now());
+    assert_eq!(pool.stats().idle, 2);
+    assert!(pool.checkout("http://a").is_none());
+    assert_eq!(pool.stats(), PoolStats { idle: 0, created: 0, reused: 0, evicted: 2 });
+
+    // 超过寿命的连接不会放回
+    let (old, _server) = connect(&listener).await;
+    pool.release("http://a", old, Instant::now() - Duration::from_secs(61));
+    assert_eq!(pool.stats().idle, 0);
+
+    let (idle, _server) = connect(&listener).await;
+    pool.set_options(PoolOptions { idle_timeout: Duration::from_millis(20), ..Default::default() });
+    pool.release("http://a", idle, Instant::now());
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert!(pool.checkout("http://a").is_none());
+    assert_eq!(pool.stats().evicted, 4);
+  }
+}
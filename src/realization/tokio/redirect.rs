@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use crate::request_init::{is_sensitive_header, Method, RedirectHeaderFilter};
+use crate::url::URL;
+
+/// 默认最多跟随的重定向次数，与规范保持一致
+pub(crate) const DEFAULT_MAX_REDIRECTS: usize = 20;
+
+const BODY_HEADERS: [&str; 5] = [
+  "Content-Type",
+  "Content-Length",
+  "Content-Encoding",
+  "Content-Language",
+  "Content-Location",
+];
+
+pub(crate) fn is_redirect(status: u16) -> bool {
+  matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+/// 按照规范判断重定向后是否需要把请求改写为不带正文的`GET`
+pub(crate) fn rewrites_to_get(status: u16, method: &Method) -> bool {
+  match status {
+    301 | 302 => *method == Method::POST,
+    303 => *method != Method::GET && *method != Method::HEAD,
+    _ => false,
+  }
+}
+
+/// 移除与请求正文相关的请求头
+pub(crate) fn remove_body_headers(headers: &mut HashMap<String, String>) {
+  headers.retain(|k, _| !BODY_HEADERS.iter().any(|name| k.eq_ignore_ascii_case(name)));
+}
+
+/// 重定向是否离开了原来的源，或者从https降级到了http
+pub(crate) fn crosses_origin(from: &URL, to: &URL) -> bool {
+  from.get_origin() != to.get_origin()
+    || (from.get_protocol() == "https:" && to.get_protocol() != "https:")
+}
+
+/// 跨源重定向时移除不应泄露给新源的请求头
+///
+/// `Host`总是被移除，会按新的URL重新生成；正文相关的请求头只在改写为`GET`时移除
+pub(crate) fn strip_headers(
+  headers: &mut HashMap<String, String>,
+  from: &URL,
+  to: &URL,
+  filter: Option<&RedirectHeaderFilter>,
+) {
+  if !crosses_origin(from, to) {
+    return;
+  }
+  headers.retain(|k, _| !k.eq_ignore_ascii_case("host"));
+  match filter {
+    Some(filter) => headers.retain(|k, _| filter(k, from, to)),
+    None => headers.retain(|k, _| !is_sensitive_header(k)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn method_rewriting() {
+    assert!(rewrites_to_get(301, &Method::POST));
+    assert!(rewrites_to_get(302, &Method::POST));
+    assert!(!rewrites_to_get(302, &Method::PUT));
+    assert!(rewrites_to_get(303, &Method::PUT));
+    assert!(!rewrites_to_get(303, &Method::HEAD));
+    assert!(!rewrites_to_get(307, &Method::POST));
+    assert!(!rewrites_to_get(308, &Method::POST));
+  }
+
+  #[test]
+  fn strip_cross_origin_credentials() {
+    let headers = HashMap::from([
+      ("authorization".to_string(), "Bearer token".to_string()),
+      ("Cookie".to_string(), "a=1".to_string()),
+      ("Proxy-Authorization".to_string(), "Basic x".to_string()),
+      ("Accept".to_string(), "*/*".to_string()),
+    ]);
+    let from = URL::new("https://api.example.com/a");
+
+    let mut same = headers.clone();
+    strip_headers(&mut same, &from, &URL::new("https://api.example.com:443/b"), None);
+    assert_eq!(same.len(), 4);
+
+    let mut cross = headers.clone();
+    strip_headers(&mut cross, &from, &URL::new("https://cdn.example.net/b"), None);
+    assert_eq!(cross.keys().collect::<Vec<_>>(), vec!["Accept"]);
+
+    let mut downgrade = headers.clone();
+    let filter: RedirectHeaderFilter = std::sync::Arc::new(|name, _, _| name.eq_ignore_ascii_case("cookie"));
+    strip_headers(&mut downgrade, &from, &URL::new("http://api.example.com/b"), Some(&filter));
+    assert_eq!(downgrade.keys().collect::<Vec<_>>(), vec!["Cookie"]);
+  }
+
+  #[test]
+  fn strip_host() {
+    let headers = HashMap::from([
+      ("Host".to_string(), "internal.example.com".to_string()),
+      ("content-type".to_string(), "application/json".to_string()),
+      ("Content-Length".to_string(), "2".to_string()),
+      ("Accept".to_string(), "*/*".to_string()),
+    ]);
+    let from = URL::new("https://api.example.com/a");
+
+    let mut same = headers.clone();
+    strip_headers(&mut same, &from, &URL::new("https://api.example.com/b"), None);
+    assert_eq!(same.len(), 4);
+
+    let filter: RedirectHeaderFilter = std::sync::Arc::new(|_, _, _| true);
+    for to in ["https://cdn.example.net/b", "http://api.example.com/b"] {
+      let mut cross = headers.clone();
+      strip_headers(&mut cross, &from, &URL::new(to), Some(&filter));
+      assert_eq!(cross.len(), 3);
+      assert!(!cross.contains_key("Host"));
+    }
+  }
+}
@@ -1,21 +1,239 @@
-use tokio::io::AsyncReadExt;
-use tokio::net::TcpStream;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use crate::abort_controller::AbortSignal;
+use crate::blob::Blob;
+use crate::form_data::{self, FormData, MultipartLimits};
+use crate::streams::ReadableStream;
+use crate::text_decoder;
+use crate::url::URL;
+use super::FetchError;
+use super::http1::ResponseHead;
+
+/// `Response::clone`为较慢一方缓存正文的默认上限
+const DEFAULT_CLONE_BUFFER: usize = 1024 * 1024;
 
 pub struct Response {
-  stream: TcpStream,
+  status: u16,
+  status_text: String,
+  headers: HashMap<String, String>,
+  url: URL,
+  redirected: bool,
+  body: Option<ReadableStream<Bytes>>,
+  body_used: bool,
+  trailers: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Response {
+  pub(crate) fn new(
+    head: ResponseHead,
+    url: URL,
+    body: ReadableStream<Bytes>,
+    trailers: Arc<Mutex<HashMap<String, String>>>,
+  ) -> Self {
+    Self {
+      status: head.status,
+      status_text: head.status_text,
+      headers: head.headers,
+      url,
+      redirected: false,
+      trailers,
+      body: Some(body),
+      body_used: false,
+    }
+  }
+
+  /// 不经过网络构造的响应，用于`data:`等非HTTP协议
+  pub(crate) fn synthetic(
+    status: u16,
+    status_text: &str,
+    headers: HashMap<String, String>,
+    url: URL,
+    body: ReadableStream<Bytes>,
+  ) -> Self {
+    Self {
+      status,
+      status_text: status_text.to_string(),
+      headers,
+      url,
+      redirected: false,
+      body: Some(body),
+      body_used: false,
+      trailers: Arc::default(),
+    }
+  }
+}
+
+impl Response {
+  pub fn status(&self) -> u16 {
+    self.status
+  }
+
+  pub fn status_text(&self) -> String {
+    self.status_text.clone()
+  }
+
+  /// 状态码是否在200-299之间
+  pub fn ok(&self) -> bool {
+    (200..300).contains(&self.status)
+  }
+
+  /// 响应头，键均为小写
+  pub fn headers(&self) -> &HashMap<String, String> {
+    &self.headers
+  }
+
+  /// 最终响应的URL，跟随重定向后为最后一次请求的地址
+  pub fn url(&self) -> &URL {
+    &self.url
+  }
+
+  /// 该响应是否经过了重定向
+  pub fn redirected(&self) -> bool {
+    self.redirected
+  }
+
+  pub(crate) fn set_redirected(&mut self, redirected: bool) {
+    self.redirected = redirected;
+  }
+
+  /// 请求的信号中止后，尚未读完的正文以`FetchError::Aborted`结束
+  pub(crate) fn abort_body_on(&mut self, signal: &AbortSignal) {
+    self.body = self.body.take().map(|body| body.abortable(signal));
+  }
+
+  /// 正文是否已经被读取，正文只能被读取一次
+  pub fn body_used(&self) -> bool {
+    self.body_used
+  }
+
+  /// chunked正文结尾的trailer字段，键均为小写，正文读完后才可用
+  pub fn trailers(&self) -> HashMap<String, String> {
+    self.trailers.lock().unwrap().clone()
+  }
 }
 
 impl Response {
-  pub async fn text(&mut self) -> Result<String, Box<dyn std::error::Error>> {
-    let mut buffer = vec![0; 1024];
-    let mut text = String::new();
-    loop {
-      let n = self.stream.read(&mut buffer).await?;
-      if n == 0 {
-        break;
-      }
-      text.push_str(&String::from_utf8_lossy(&buffer[..n]));
+  /// 读取完整的正文并解码为字符串
+  ///
+  /// 编码优先由BOM决定，其次是`Content-Type`的`charset`参数，默认为UTF-8；
+  /// Shift_JIS、GBK等旧式编码需要启用`encoding`特性
+  pub async fn text(&mut self) -> Result<String, FetchError> {
+    let body = self.read_body().await?;
+    Ok(text_decoder::decode(&body, self.headers.get("content-type").map(|s| s.as_str())))
+  }
+
+  /// 取出正文的流，之后正文被视为已读取
+  ///
+  /// 返回的流按需从连接读取数据，可以在有限内存下转存大文件
+  ///
+  /// # Example
+  /// ```no_run
+  /// use fetch_js::fetch;
+  /// use fetch_js::request_init::RequestInit;
+  ///
+  /// #[tokio::main]
+  /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+  ///   let mut response = fetch("http://example.com/large.iso", RequestInit::default()).await?;
+  ///   let mut reader = response.body()?.into_async_read();
+  ///   let mut file = tokio::fs::File::create("large.iso").await?;
+  ///   tokio::io::copy(&mut reader, &mut file).await?;
+  ///   Ok(())
+  /// }
+  /// ```
+  pub fn body(&mut self) -> Result<ReadableStream<Bytes>, FetchError> {
+    if self.body_used {
+      return Err(FetchError::BodyUsed);
+    }
+    self.body_used = true;
+    self.body.take().ok_or(FetchError::BodyUsed)
+  }
+
+  /// 复制响应，两份正文可以分别读取，正文已被读取时返回`FetchError::BodyUsed`
+  ///
+  /// 正文通过`ReadableStream::tee_with_limit`分成两份，为较慢一方最多缓存1MiB，
+  /// 超出时较快的一方读取失败而另一方不受影响
+  ///
+  /// # Example
+  /// ```no_run
+  /// use fetch_js::fetch;
+  /// use fetch_js::request_init::RequestInit;
+  ///
+  /// #[tokio::main]
+  /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+  ///   let mut response = fetch("http://example.com", RequestInit::default()).await?;
+  ///   let mut copy = response.clone()?;
+  ///   println!("{}", copy.text().await?);
+  ///   let text = response.text().await?;
+  ///   Ok(())
+  /// }
+  /// ```
+  #[allow(clippy::should_implement_trait)]
+  pub fn clone(&mut self) -> Result<Self, FetchError> {
+    self.clone_with_limit(DEFAULT_CLONE_BUFFER)
+  }
+
+  /// 与`clone`相同，为较慢一方最多缓存`max_buffered`字节
+  pub fn clone_with_limit(&mut self, max_buffered: usize) -> Result<Self, FetchError> {
+    if self.body_used {
+      return Err(FetchError::BodyUsed);
+    }
+    let body = match self.body.take() {
+      Some(body) => {
+        let (body, copy) = body.tee_with_limit(max_buffered);
+        self.body = Some(body);
+        Some(copy)
+      },
+      None => None,
+    };
+    Ok(Self {
+      status: self.status,
+      status_text: self.status_text.clone(),
+      headers: self.headers.clone(),
+      url: self.url.clone(),
+      redirected: self.redirected,
+      body,
+      body_used: false,
+      trailers: self.trailers.clone(),
+    })
+  }
+
+  /// 读取完整的正文
+  pub async fn bytes(&mut self) -> Result<Bytes, FetchError> {
+    Ok(Bytes::from(self.read_body().await?))
+  }
+
+  /// 读取完整的正文，对应JS中的`arrayBuffer()`
+  pub async fn array_buffer(&mut self) -> Result<Vec<u8>, FetchError> {
+    self.read_body().await
+  }
+
+  /// 读取完整的正文为`Blob`，类型取自`Content-Type`
+  pub async fn blob(&mut self) -> Result<Blob, FetchError> {
+    let body = self.read_body().await?;
+    Ok(Blob::new(body, self.headers.get("content-type").map(|s| s.as_str()).unwrap_or_default()))
+  }
+
+  /// 按`Content-Type`将正文解析为`FormData`，支持`multipart/form-data`和`application/x-www-form-urlencoded`
+  ///
+  /// `multipart/form-data`边读取边解析，使用默认的`MultipartLimits`
+  pub async fn form_data(&mut self) -> Result<FormData, FetchError> {
+    self.form_data_with_limits(MultipartLimits::default()).await
+  }
+
+  /// 与`form_data`相同，使用指定的字段数和字段大小限制
+  pub async fn form_data_with_limits(&mut self, limits: MultipartLimits) -> Result<FormData, FetchError> {
+    let body = self.body()?;
+    Ok(form_data::parse(body, self.headers.get("content-type").map(|s| s.as_str()), limits).await?)
+  }
+
+  async fn read_body(&mut self) -> Result<Vec<u8>, FetchError> {
+    let mut stream = self.body()?;
+    let mut body = Vec::new();
+    while let Some(chunk) = stream.next().await {
+      body.extend_from_slice(&chunk?);
     }
-    Ok(text)
+    Ok(body)
   }
-}
\ No newline at end of file
+}
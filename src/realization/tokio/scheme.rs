@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::ops::Range;
+use bytes::Bytes;
+use crate::blob::Blob;
+use crate::request::Request;
+use crate::request_init::Method;
+use crate::streams::ReadableStream;
+use crate::url::URL;
+use super::{FetchError, Response};
+
+/// 将`data:` URL的内容作为`200`响应返回
+pub(super) fn fetch_data_url(url: &URL) -> Result<Response, FetchError> {
+  let data = crate::data_url::parse(url)
+    .ok_or_else(|| FetchError::Network(format!("invalid data: URL: {}", url.get_href())))?;
+  let headers = HashMap::from([("content-type".to_string(), data.mime_type)]);
+  let body = ReadableStream::from_stream(futures_util::stream::iter([Ok(data.body)]));
+  Ok(Response::synthetic(200, "OK", headers, url.clone(), body))
+}
+
+/// 读取`file:` URL指向的本地文件，只支持`GET`和`HEAD`，需要请求设置`allow_file_urls`
+///
+/// `Request`不是`Sync`的，先取出需要的字段，返回的future不持有`request`
+pub(super) fn fetch_file_url(request: &Request) -> impl Future<Output = Result<Response, FetchError>> + Send {
+  let url = request.url().clone();
+  let method = request.method().clone();
+  let allowed = request.allow_file_urls();
+  let range = request.headers().iter()
+    .find(|(name, _)| name.eq_ignore_ascii_case("range"))
+    .map(|(_, value)| value.clone());
+  async move {
+    if !allowed {
+      return Err(FetchError::Network("file: URLs are not allowed unless allow_file_urls is set".to_string()));
+    }
+    if method != Method::GET && method != Method::HEAD {
+      return Err(FetchError::Network(format!("method {} is not allowed for file: URLs", method.as_str())));
+    }
+    let path = url.to_file_path()
+      .ok_or_else(|| FetchError::Network(format!("cannot fetch remote file: URL: {}", url.get_href())))?;
+    let file = crate::blob::File::from_path(&path).await?;
+    Ok(blob_response(&url, &method, &file, range.as_deref()))
+  }
+}
+
+/// 读取`URL::create_object_url`创建的blob URL，只支持`GET`
+pub(super) fn fetch_blob_url(request: &Request) -> Result<Response, FetchError> {
+  let url = request.url();
+  if *request.method() != Method::GET {
+    return Err(FetchError::Network(format!("method {} is not allowed for blob: URLs", request.method().as_str())));
+  }
+  let blob = crate::url::resolve_object_url(url)
+    .ok_or_else(|| FetchError::Network(format!("blob URL not found or revoked: {}", url.get_href())))?;
+  let range = request.headers().iter()
+    .find(|(name, _)| name.eq_ignore_ascii_case("range"))
+    .map(|(_, value)| value.as_str());
+  Ok(blob_response(url, request.method(), &blob, range))
+}
+
+/// 以`Blob`为正文构造响应，支持单个`Range`
+fn blob_response(url: &URL, method: &Method, blob: &Blob, range: Option<&str>) -> Response {
+  let size = blob.size();
+  let mut headers = HashMap::new();
+  if !blob.get_type().is_empty() {
+    headers.insert("content-type".to_string(), blob.get_type());
+  }
+  let (status, status_text, body) = match byte_range(range, size) {
+    ByteRange::Full => (200, "OK", blob.clone()),
+    ByteRange::Partial(range) => {
+      let content_range = format!("bytes {}-{}/{}", range.start, range.end - 1, size);
+      headers.insert("content-range".to_string(), content_range);
+      (206, "Partial Content", blob.slice(Some(range.start as i64), Some(range.end as i64), None))
+    },
+    ByteRange::Unsatisfiable => {
+      headers.insert("content-range".to_string(), format!("bytes */{}", size));
+      (416, "Range Not Satisfiable", Blob::default())
+    },
+  };
+  headers.insert("content-length".to_string(), body.size().to_string());
+  let body = match *method {
+    Method::HEAD => ReadableStream::from_stream(futures_util::stream::empty::<std::io::Result<Bytes>>()),
+    _ => body.stream(),
+  };
+  Response::synthetic(status, status_text, headers, url.clone(), body)
+}
+
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+  Full,
+  Partial(Range<u64>),
+  Unsatisfiable,
+}
+
+/// 解析单个`bytes=start-end`范围，格式无效或包含多个范围时忽略，返回完整内容
+fn byte_range(header: Option<&str>, size: u64) -> ByteRange {
+  let Some(header) = header else {
+    return ByteRange::Full;
+  };
+  let spec = match header.trim().get(..6) {
+    Some(unit) if unit.eq_ignore_ascii_case("bytes=") => header.trim()[6..].trim(),
+    _ => return ByteRange::Full,
+  };
+  let Some((start, end)) = spec.split_once('-') else {
+    return ByteRange::Full;
+  };
+  let number = |s: &str| match s.trim() {
+    "" => Ok(None),
+    s if s.bytes().all(|b| b.is_ascii_digit()) => s.parse().map(Some).map_err(|_| ()),
+    _ => Err(()),
+  };
+  let (start, end) = match (number(start), number(end)) {
+    (Ok(start), Ok(end)) => (start, end),
+    _ => return ByteRange::Full,
+  };
+  match (start, end) {
+    (Some(start), _) if start >= size => ByteRange::Unsatisfiable,
+    (Some(start), Some(end)) if start > end => ByteRange::Full,
+    (Some(start), end) => ByteRange::Partial(start..end.map_or(size, |end: u64| end.saturating_add(1).min(size))),
+    (None, Some(0)) => ByteRange::Unsatisfiable,
+    (None, Some(_)) if size == 0 => ByteRange::Unsatisfiable,
+    (None, Some(suffix)) => ByteRange::Partial(size.saturating_sub(suffix)..size),
+    (None, None) => ByteRange::Full,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn range() {
+    assert_eq!(byte_range(None, 10), ByteRange::Full);
+    assert_eq!(byte_range(Some("bytes=2-4"), 10), ByteRange::Partial(2..5));
+    assert_eq!(byte_range(Some("bytes=2-"), 10), ByteRange::Partial(2..10));
+    assert_eq!(byte_range(Some("Bytes= 5-100"), 10), ByteRange::Partial(5..10));
+    assert_eq!(byte_range(Some("bytes=-3"), 10), ByteRange::Partial(7..10));
+    assert_eq!(byte_range(Some("bytes=-30"), 10), ByteRange::Partial(0..10));
+    assert_eq!(byte_range(Some("bytes=10-"), 10), ByteRange::Unsatisfiable);
+    assert_eq!(byte_range(Some("bytes=-0"), 10), ByteRange::Unsatisfiable);
+    assert_eq!(byte_range(Some("bytes=4-2"), 10), ByteRange::Full);
+    assert_eq!(byte_range(Some("bytes=0-1,3-4"), 10), ByteRange::Full);
+    assert_eq!(byte_range(Some("items=0-1"), 10), ByteRange::Full);
+  }
+}
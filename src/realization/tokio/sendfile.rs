@@ -0,0 +1,37 @@
+use std::io;
+use std::os::fd::AsRawFd;
+use tokio::io::Interest;
+use tokio::net::TcpStream;
+
+/// 单次`sendfile`调用最多发送的字节数，与Linux内核的限制一致
+const MAX_CHUNK: u64 = 0x7fff_f000;
+
+/// 通过`sendfile`把文件从`offset`开始的`length`字节直接由内核发送到连接
+///
+/// 文件系统不支持`sendfile`且尚未发送任何数据时返回`false`，由调用方改用普通的读写
+pub(crate) async fn send_file(stream: &TcpStream, file: &std::fs::File, offset: u64, length: u64) -> io::Result<bool> {
+  let end = offset + length;
+  let mut position = offset;
+  while position < end {
+    stream.writable().await?;
+    let result = stream.try_io(Interest::WRITABLE, || {
+      let mut off = position as libc::off_t;
+      let count = (end - position).min(MAX_CHUNK) as usize;
+      // SAFETY: 两个文件描述符在调用期间都由借用保证有效，`off`是有效的可写指针
+      let sent = unsafe { libc::sendfile(stream.as_raw_fd(), file.as_raw_fd(), &mut off, count) };
+      if sent < 0 {
+        Err(io::Error::last_os_error())
+      } else {
+        Ok(sent as u64)
+      }
+    });
+    match result {
+      Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file is shorter than the body length")),
+      Ok(sent) => position += sent,
+      Err(e) if e.kind() == io::ErrorKind::WouldBlock => {},
+      Err(e) if position == offset && matches!(e.raw_os_error(), Some(libc::EINVAL | libc::ENOSYS)) => return Ok(false),
+      Err(e) => return Err(e),
+    }
+  }
+  Ok(true)
+}
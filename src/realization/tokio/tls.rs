@@ -0,0 +1,451 @@
+mod verifier;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use super::FetchError;
+use verifier::PinningVerifier;
+
+/// `https:`连接的TLS配置，可以添加私有CA的根证书、出示客户端证书（mTLS）和按主机固定公钥
+///
+/// 同一个配置用于多个请求时，只会在第一次连接时生成rustls的配置
+///
+/// # Example
+/// ```
+/// use fetch_js::request_init::RequestInit;
+/// use fetch_js::TlsOptions;
+///
+/// let tls = TlsOptions::new()
+///   .pin_sha256("api.example.com", "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=")
+///   .unwrap();
+/// let init = RequestInit::builder().tls(tls).build().unwrap();
+/// assert!(init.tls.is_some());
+///
+/// assert!(TlsOptions::new().add_root_certificates_pem(b"not a certificate").is_err());
+/// assert!(TlsOptions::new().pin_sha256("api.example.com", "AAAA").is_err());
+/// ```
+#[derive(Clone)]
+pub struct TlsOptions {
+  roots: Vec<CertificateDer<'static>>,
+  default_roots: bool,
+  identity: Option<Arc<Identity>>,
+  pins: HashMap<String, Vec<[u8; 32]>>,
+  danger_accept_invalid_certs: bool,
+  config: Arc<OnceLock<Arc<ClientConfig>>>,
+  /// 每次修改都会换一个编号，编号相同的配置才能共用连接池中的连接
+  id: u64,
+}
+
+/// 客户端证书链和对应的私钥
+struct Identity {
+  chain: Vec<CertificateDer<'static>>,
+  key: PrivateKeyDer<'static>,
+}
+
+impl Default for TlsOptions {
+  fn default() -> Self {
+    Self {
+      roots: vec![],
+      default_roots: true,
+      identity: None,
+      pins: HashMap::new(),
+      danger_accept_invalid_certs: false,
+      config: Arc::default(),
+      id: next_id(),
+    }
+  }
+}
+
+impl TlsOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// 信任PEM中的所有证书，可以多次调用；PEM中没有证书或证书无效时返回错误
+  pub fn add_root_certificates_pem(mut self, pem: &[u8]) -> Result<Self, FetchError> {
+    let certificates = CertificateDer::pem_slice_iter(pem)
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|e| FetchError::Tls(format!("invalid root certificate: {}", e)))?;
+    if certificates.is_empty() {
+      return Err(FetchError::Tls("no certificate found in PEM".to_string()));
+    }
+    let mut roots = RootCertStore::empty();
+    for certificate in &certificates {
+      roots.add(certificate.clone()).map_err(|e| FetchError::Tls(format!("invalid root certificate: {}", e)))?;
+    }
+    self.roots.extend(certificates);
+    Ok(self.changed())
+  }
+
+  /// 是否信任系统的根证书（系统中没有可用的证书时为webpki-roots的根证书），默认信任
+  pub fn default_roots(mut self, enabled: bool) -> Self {
+    self.default_roots = enabled;
+    self.changed()
+  }
+
+  /// 服务器要求客户端证书时出示的证书链和私钥，私钥可以是PKCS#8、PKCS#1或SEC1格式
+  pub fn identity_pem(mut self, chain_pem: &[u8], key_pem: &[u8]) -> Result<Self, FetchError> {
+    let chain = CertificateDer::pem_slice_iter(chain_pem)
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|e| FetchError::Tls(format!("invalid client certificate: {}", e)))?;
+    if chain.is_empty() {
+      return Err(FetchError::Tls("no certificate found in PEM".to_string()));
+    }
+    let key = PrivateKeyDer::from_pem_slice(key_pem)
+      .map_err(|e| FetchError::Tls(format!("invalid private key: {}", e)))?;
+    provider().key_provider.load_private_key(key.clone_key())
+      .map_err(|e| FetchError::Tls(format!("invalid private key: {}", e)))?;
+    self.identity = Some(Arc::new(Identity { chain, key }));
+    Ok(self.changed())
+  }
+
+  /// 固定`host`的服务器公钥，`hash`是证书中SubjectPublicKeyInfo的SHA-256哈希的base64编码；
+  /// 同一主机可以固定多个公钥，服务器的公钥与其中任意一个一致即可
+  pub fn pin_sha256(mut self, host: &str, hash: &str) -> Result<Self, FetchError> {
+    let hash = crate::base64::decode(hash.as_bytes())
+      .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+      .ok_or_else(|| FetchError::Tls(format!("invalid SHA-256 pin: {}", hash)))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+    self.pins.entry(host).or_default().push(hash);
+    Ok(self.changed())
+  }
+
+  /// 不校验服务器证书的签发者、有效期和主机名，只应在本地开发时使用；固定的公钥仍会被检查
+  pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+    self.danger_accept_invalid_certs = accept;
+    self.changed()
+  }
+
+  pub(crate) fn id(&self) -> u64 {
+    self.id
+  }
+
+  /// 生成rustls的配置，结果会被缓存
+  pub(crate) fn client_config(&self) -> Result<Arc<ClientConfig>, FetchError> {
+    if let Some(config) = self.config.get() {
+      return Ok(config.clone());
+    }
+    let config = Arc::new(self.build()?);
+    Ok(self.config.get_or_init(|| config).clone())
+  }
+
+  fn build(&self) -> Result<ClientConfig, FetchError> {
+    let provider = provider();
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+      .with_safe_default_protocol_versions()
+      .map_err(|e| FetchError::Tls(e.to_string()))?;
+    let mut roots = if self.default_roots {
+      default_roots().clone()
+    } else {
+      RootCertStore::empty()
+    };
+    roots.add_parsable_certificates(self.roots.iter().cloned());
+    let builder = if self.pins.is_empty() && !self.danger_accept_invalid_certs {
+      builder.with_root_certificates(roots)
+    } else {
+      let verifier = PinningVerifier::new(roots, self.pins.clone(), self.danger_accept_invalid_certs, provider)?;
+      builder.dangerous().with_custom_certificate_verifier(Arc::new(verifier))
+    };
+    let mut config = match self.identity {
+      Some(ref identity) => builder.with_client_auth_cert(identity.chain.clone(), identity.key.clone_key())
+        .map_err(|e| FetchError::Tls(e.to_string()))?,
+      None => builder.with_no_client_auth(),
+    };
+    #[cfg(feature = "http2")]
+    config.alpn_protocols.push(b"h2".to_vec());
+    config.alpn_protocols.push(b"http/1.1".to_vec());
+    Ok(config)
+  }
+
+  /// 修改后之前生成的配置不再适用
+  fn changed(mut self) -> Self {
+    self.config = Arc::default();
+    self.id = next_id();
+    self
+  }
+}
+
+impl fmt::Debug for TlsOptions {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("TlsOptions")
+      .field("roots", &self.roots.len())
+      .field("default_roots", &self.default_roots)
+      .field("identity", &self.identity.is_some())
+      .field("pins", &self.pins.keys().collect::<Vec<_>>())
+      .field("danger_accept_invalid_certs", &self.danger_accept_invalid_certs)
+      .finish()
+  }
+}
+
+fn next_id() -> u64 {
+  static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+  NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// 使用ring作为加密实现
+fn provider() -> Arc<CryptoProvider> {
+  Arc::new(rustls::crypto::ring::default_provider())
+}
+
+static DEFAULT_ROOTS: OnceLock<RootCertStore> = OnceLock::new();
+
+/// 系统的根证书，系统中没有可用的证书时使用webpki-roots内置的Mozilla根证书；只在第一次使用时读取
+fn default_roots() -> &'static RootCertStore {
+  DEFAULT_ROOTS.get_or_init(|| {
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    if roots.is_empty() {
+      roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+    roots
+  })
+}
+
+/// 读取系统的根证书需要读文件，在阻塞线程中提前读取，避免第一次`https:`连接阻塞异步工作线程
+pub(crate) async fn load_default_roots() {
+  if DEFAULT_ROOTS.get().is_none() {
+    let _ = tokio::task::spawn_blocking(default_roots).await;
+  }
+}
+
+/// 请求没有指定TLS配置时使用的默认配置
+pub(crate) fn default_options() -> &'static TlsOptions {
+  static OPTIONS: OnceLock<TlsOptions> = OnceLock::new();
+  OPTIONS.get_or_init(TlsOptions::default)
+}
+
+/// 在TCP连接上进行TLS握手，`hostname`同时用于SNI和证书校验
+pub(crate) async fn handshake(
+  stream: TcpStream,
+  hostname: &str,
+  config: Arc<ClientConfig>,
+) -> Result<TlsStream<TcpStream>, FetchError> {
+  let hostname = hostname.trim_start_matches('[').trim_end_matches(']');
+  let server_name = ServerName::try_from(hostname.to_string())
+    .map_err(|e| FetchError::Tls(format!("invalid server name {}: {}", hostname, e)))?;
+  Ok(TlsConnector::from(config).connect(server_name, stream).await?)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+  use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair, PublicKeyData};
+  use rustls::pki_types::PrivatePkcs8KeyDer;
+  use rustls::server::WebPkiClientVerifier;
+  use rustls::ServerConfig;
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+  use tokio::net::TcpListener;
+  use crate::request_init::RequestInit;
+  use crate::url::URL;
+
+  /// 本地生成的CA，以及由它签发的服务器（`localhost`）和客户端证书
+  pub(crate) struct Certificates {
+    pub(crate) ca_pem: String,
+    pub(crate) ca: CertificateDer<'static>,
+    pub(crate) chain: Vec<CertificateDer<'static>>,
+    pub(crate) key: PrivateKeyDer<'static>,
+    /// 服务器公钥的SHA-256哈希，base64编码
+    pub(crate) pin: String,
+    pub(crate) client_pem: String,
+    pub(crate) client_key_pem: String,
+  }
+
+  pub(crate) fn certificates() -> Certificates {
+    let mut params = CertificateParams::new(vec![]).unwrap();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap();
+    let key = KeyPair::generate().unwrap();
+    let leaf = CertificateParams::new(vec!["localhost".to_string()]).unwrap().signed_by(&key, &ca).unwrap();
+    let client_key = KeyPair::generate().unwrap();
+    let client = CertificateParams::new(vec!["client".to_string()]).unwrap().signed_by(&client_key, &ca).unwrap();
+    let pin = ring::digest::digest(&ring::digest::SHA256, &key.subject_public_key_info());
+    Certificates {
+      ca_pem: ca.pem(),
+      ca: ca.der().clone(),
+      chain: vec![leaf.der().clone()],
+      key: PrivatePkcs8KeyDer::from(key.serialize_der()).into(),
+      pin: crate::base64::encode(pin.as_ref()),
+      client_pem: client.pem(),
+      client_key_pem: client_key.serialize_pem(),
+    }
+  }
+
+  /// 启动一个只处理一个连接的本地TLS服务器，返回端口和握手时收到的SNI；
+  /// `client_auth`时要求客户端出示由同一CA签发的证书
+  pub(crate) async fn serve_tls(
+    certificates: &Certificates,
+    client_auth: bool,
+    response: &'static str,
+  ) -> (u16, tokio::task::JoinHandle<Option<String>>) {
+    let builder = ServerConfig::builder_with_provider(provider())
+      .with_safe_default_protocol_versions()
+      .unwrap();
+    let builder = if client_auth {
+      let mut roots = RootCertStore::empty();
+      roots.add(certificates.ca.clone()).unwrap();
+      builder.with_client_cert_verifier(WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider()).build().unwrap())
+    } else {
+      builder.with_no_client_auth()
+    };
+    let mut config = builder.with_single_cert(certificates.chain.clone(), certificates.key.clone_key()).unwrap();
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = tokio::spawn(async move {
+      let (socket, _) = listener.accept().await.unwrap();
+      let mut stream = tokio_rustls::TlsAcceptor::from(Arc::new(config)).accept(socket).await.ok()?;
+      let server_name = stream.get_ref().1.server_name().map(str::to_string);
+      let mut request = Vec::new();
+      let mut buffer = [0; 1024];
+      while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buffer).await.ok()?;
+        request.extend_from_slice(&buffer[..n]);
+      }
+      stream.write_all(response.as_bytes()).await.unwrap();
+      stream.shutdown().await.unwrap();
+      server_name
+    });
+    (port, handle)
+  }
+
+  const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+
+  async fn fetch_text(port: u16, host: &str, tls: TlsOptions) -> Result<String, FetchError> {
+    let url = URL::new(&format!("https://{}:{}/", host, port));
+    let init = RequestInit::builder().tls(tls).build().unwrap();
+    crate::fetch(url, init).await?.text().await
+  }
+
+  fn private_ca(certificates: &Certificates) -> TlsOptions {
+    TlsOptions::new().default_roots(false).add_root_certificates_pem(certificates.ca_pem.as_bytes()).unwrap()
+  }
+
+  #[tokio::test]
+  async fn handshake_with_custom_ca() {
+    let certificates = certificates();
+    let (port, server) = serve_tls(&certificates, false, OK).await;
+    let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let config = private_ca(&certificates).client_config().unwrap();
+    let mut stream = handshake(stream, "localhost", config).await.unwrap();
+    assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"http/1.1"[..]));
+    stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.ends_with("\r\n\r\nok"));
+    assert_eq!(server.await.unwrap().as_deref(), Some("localhost"));
+  }
+
+  #[test]
+  fn config_id() {
+    let options = TlsOptions::new();
+    assert_eq!(options.clone().id(), options.id());
+    assert_ne!(options.clone().default_roots(false).id(), options.id());
+    assert_ne!(TlsOptions::new().id(), options.id());
+  }
+
+  #[tokio::test]
+  async fn unknown_issuer() {
+    let certificates = certificates();
+    let (port, server) = serve_tls(&certificates, false, OK).await;
+    let result = fetch_text(port, "localhost", TlsOptions::new()).await;
+    assert!(matches!(result, Err(FetchError::Tls(ref message)) if message.contains("UnknownIssuer")));
+    assert_eq!(server.await.unwrap(), None);
+  }
+
+  #[tokio::test]
+  async fn client_certificate() {
+    let certificates = certificates();
+    let (port, server) = serve_tls(&certificates, true, OK).await;
+    let tls = private_ca(&certificates)
+      .identity_pem(certificates.client_pem.as_bytes(), certificates.client_key_pem.as_bytes())
+      .unwrap();
+    assert_eq!(fetch_text(port, "localhost", tls).await.unwrap(), "ok");
+    assert!(server.await.unwrap().is_some());
+
+    let (port, server) = serve_tls(&certificates, true, OK).await;
+    assert!(fetch_text(port, "localhost", private_ca(&certificates)).await.is_err());
+    assert_eq!(server.await.unwrap(), None);
+
+    let pem = certificates.client_pem.as_bytes();
+    assert!(TlsOptions::new().identity_pem(pem, b"").is_err());
+    assert!(TlsOptions::new().identity_pem(b"", certificates.client_key_pem.as_bytes()).is_err());
+  }
+
+  #[tokio::test]
+  async fn pinning() {
+    let certificates = certificates();
+    let (port, _) = serve_tls(&certificates, false, OK).await;
+    let tls = private_ca(&certificates)
+      .pin_sha256("LOCALHOST", &certificates.pin)
+      .unwrap();
+    assert_eq!(fetch_text(port, "localhost", tls).await.unwrap(), "ok");
+
+    let (port, _) = serve_tls(&certificates, false, OK).await;
+    let tls = private_ca(&certificates)
+      .pin_sha256("localhost", "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=")
+      .unwrap();
+    let result = fetch_text(port, "localhost", tls).await;
+    assert!(matches!(result, Err(FetchError::Tls(ref message)) if message.contains("pinned")));
+
+    // 固定其他主机的公钥不影响这个主机
+    let (port, _) = serve_tls(&certificates, false, OK).await;
+    let tls = private_ca(&certificates)
+      .pin_sha256("example.com", "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=")
+      .unwrap();
+    assert_eq!(fetch_text(port, "localhost", tls).await.unwrap(), "ok");
+  }
+
+  #[tokio::test]
+  async fn accept_invalid_certs() {
+    let certificates = certificates();
+    // 证书既不被信任，也不包含127.0.0.1
+    let (port, _) = serve_tls(&certificates, false, OK).await;
+    let tls = TlsOptions::new().danger_accept_invalid_certs(true);
+    assert_eq!(fetch_text(port, "127.0.0.1", tls).await.unwrap(), "ok");
+
+    let (port, _) = serve_tls(&certificates, false, OK).await;
+    let tls = TlsOptions::new()
+      .danger_accept_invalid_certs(true)
+      .pin_sha256("127.0.0.1", "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=")
+      .unwrap();
+    assert!(matches!(fetch_text(port, "127.0.0.1", tls).await, Err(FetchError::Tls(_))));
+  }
+
+  #[cfg(feature = "http2")]
+  #[tokio::test]
+  async fn negotiate_http2() {
+    let certificates = certificates();
+    let mut config = ServerConfig::builder_with_provider(provider())
+      .with_safe_default_protocol_versions()
+      .unwrap()
+      .with_no_client_auth()
+      .with_single_cert(certificates.chain.clone(), certificates.key.clone_key())
+      .unwrap();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+      let (socket, _) = listener.accept().await.unwrap();
+      let stream = tokio_rustls::TlsAcceptor::from(Arc::new(config)).accept(socket).await.unwrap();
+      assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+      super::super::http2::tests::serve_connection(stream, 0, usize::MAX).await;
+    });
+    // 服务器只接受一个连接，第二个请求复用同一个HTTP/2连接
+    let pool = crate::ConnectionPool::default();
+    let tls = private_ca(&certificates);
+    for _ in 0..2 {
+      let init = RequestInit::builder().tls(tls.clone()).pool(pool.clone()).build().unwrap();
+      let mut response = crate::fetch(URL::new(&format!("https://localhost:{}/", port)), init).await.unwrap();
+      assert_eq!(response.text().await.unwrap(), "connection 0");
+    }
+    assert_eq!(pool.stats().created, 1);
+  }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, Error, RootCertStore, SignatureScheme};
+use super::super::FetchError;
+
+/// 校验证书链后再检查服务器公钥是否与固定的哈希一致
+///
+/// 没有`inner`时不校验证书链，但仍会检查握手签名和固定的公钥
+#[derive(Debug)]
+pub(super) struct PinningVerifier {
+  inner: Option<Arc<WebPkiServerVerifier>>,
+  pins: HashMap<String, Vec<[u8; 32]>>,
+  algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl PinningVerifier {
+  pub(super) fn new(
+    roots: RootCertStore,
+    pins: HashMap<String, Vec<[u8; 32]>>,
+    accept_invalid_certs: bool,
+    provider: Arc<CryptoProvider>,
+  ) -> Result<Self, FetchError> {
+    let inner = if accept_invalid_certs {
+      None
+    } else {
+      let verifier = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .map_err(|e| FetchError::Tls(e.to_string()))?;
+      Some(verifier)
+    };
+    Ok(Self {
+      inner,
+      pins,
+      algorithms: provider.signature_verification_algorithms,
+    })
+  }
+}
+
+/// 证书中SubjectPublicKeyInfo的SHA-256哈希
+pub(super) fn spki_sha256(cert: &CertificateDer<'_>) -> Result<[u8; 32], Error> {
+  let cert = webpki::EndEntityCert::try_from(cert)
+    .map_err(|_| Error::InvalidCertificate(CertificateError::BadEncoding))?;
+  let digest = ring::digest::digest(&ring::digest::SHA256, cert.subject_public_key_info().as_ref());
+  let mut hash = [0; 32];
+  hash.copy_from_slice(digest.as_ref());
+  Ok(hash)
+}
+
+impl ServerCertVerifier for PinningVerifier {
+  fn verify_server_cert(
+    &self,
+    end_entity: &CertificateDer<'_>,
+    intermediates: &[CertificateDer<'_>],
+    server_name: &ServerName<'_>,
+    ocsp_response: &[u8],
+    now: UnixTime,
+  ) -> Result<ServerCertVerified, Error> {
+    if let Some(ref inner) = self.inner {
+      inner.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+    }
+    let host = server_name.to_str();
+    if let Some(pins) = self.pins.get(host.as_ref()) {
+      if !pins.contains(&spki_sha256(end_entity)?) {
+        return Err(Error::General(format!("public key of {} does not match any pinned hash", host)));
+      }
+    }
+    Ok(ServerCertVerified::assertion())
+  }
+
+  fn verify_tls12_signature(
+    &self,
+    message: &[u8],
+    cert: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, Error> {
+    verify_tls12_signature(message, cert, dss, &self.algorithms)
+  }
+
+  fn verify_tls13_signature(
+    &self,
+    message: &[u8],
+    cert: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, Error> {
+    verify_tls13_signature(message, cert, dss, &self.algorithms)
+  }
+
+  fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+    self.algorithms.supported_schemes()
+  }
+}
@@ -0,0 +1,560 @@
+use std::collections::HashMap;
+use bytes::Bytes;
+use std::time::Duration;
+use thiserror::Error;
+use crate::abort_controller::AbortSignal;
+use crate::blob::Blob;
+use crate::form_data::{self, FormData, FormDataError, MultipartLimits};
+use crate::request_init::*;
+use crate::streams::ReadableStream;
+use crate::url::URL;
+
+#[derive(Debug, Error)]
+pub enum RequestError {
+  #[error("invalid request: {0}")]
+  Invalid(String),
+  #[error("body has already been used")]
+  BodyUsed,
+  #[error("failed to read body: {0}")]
+  Body(#[source] std::io::Error),
+  #[error("invalid form data: {0}")]
+  FormData(#[from] FormDataError),
+}
+
+/// `Request::new`和`fetch`接受的输入，可以是URL、URL字符串或者已有的`Request`
+#[allow(clippy::large_enum_variant)]
+pub enum RequestInfo {
+  Url(URL),
+  Request(Request),
+}
+
+impl From<URL> for RequestInfo {
+  fn from(url: URL) -> Self {
+    RequestInfo::Url(url)
+  }
+}
+
+impl From<&URL> for RequestInfo {
+  fn from(url: &URL) -> Self {
+    RequestInfo::Url(url.clone())
+  }
+}
+
+impl From<&str> for RequestInfo {
+  fn from(url: &str) -> Self {
+    RequestInfo::Url(URL::new(url))
+  }
+}
+
+impl From<String> for RequestInfo {
+  fn from(url: String) -> Self {
+    RequestInfo::Url(URL::new(&url))
+  }
+}
+
+impl From<Request> for RequestInfo {
+  fn from(request: Request) -> Self {
+    RequestInfo::Request(request)
+  }
+}
+
+/// 对应JS中的`Request`类，保存经过校验的请求参数
+pub struct Request {
+  method: Method,
+  url: URL,
+  headers: HashMap<String, String>,
+  body: Option<BodyInit>,
+  body_used: bool,
+  mode: RequestMode,
+  credentials: RequestCredentials,
+  cache: RequestCache,
+  redirect: RequestRedirect,
+  referrer: String,
+  referrer_policy: Option<ReferrerPolicy>,
+  integrity: String,
+  keepalive: bool,
+  signal: AbortSignal,
+  max_redirects: Option<usize>,
+  redirect_header_filter: Option<RedirectHeaderFilter>,
+  browser_compatible: bool,
+  timeout: Option<Duration>,
+  duplex: Option<RequestDuplex>,
+  allow_file_urls: bool,
+  #[cfg(feature = "tls")]
+  tls: Option<crate::TlsOptions>,
+  #[cfg(feature = "tokio-fetch")]
+  pool: Option<crate::ConnectionPool>,
+}
+
+impl Request {
+  /// 创建请求，`input`为`Request`时`init`中设置的字段会覆盖原请求的对应字段
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::request::Request;
+  /// use fetch_js::request_init::{Method, RequestInit, RequestMode};
+  ///
+  /// let request = Request::new("http://example.com/api", RequestInit {
+  ///   method: Some(Method::POST),
+  ///   body: Some("{}".into()),
+  ///   ..Default::default()
+  /// }).unwrap();
+  /// assert_eq!(request.method(), &Method::POST);
+  /// assert_eq!(request.mode(), RequestMode::Cors);
+  ///
+  /// let copy = Request::new(request, RequestInit::default()).unwrap();
+  /// assert_eq!(copy.url().get_href(), "http://example.com/api");
+  /// assert_eq!(copy.method(), &Method::POST);
+  /// ```
+  pub fn new(input: impl Into<RequestInfo>, init: RequestInit) -> Result<Self, RequestError> {
+    let mut request = match input.into() {
+      RequestInfo::Url(url) => Self::from_url(url),
+      RequestInfo::Request(request) => {
+        if request.body_used {
+          return Err(RequestError::BodyUsed);
+        }
+        request
+      },
+    };
+    if let Some(method) = init.method {
+      request.method = method;
+    }
+    if let Some(headers) = init.headers {
+      request.headers = headers;
+    }
+    if init.body.is_some() {
+      request.body = init.body;
+    }
+    if let Some(mode) = init.mode {
+      request.mode = mode;
+    }
+    if let Some(credentials) = init.credentials {
+      request.credentials = credentials;
+    }
+    if let Some(cache) = init.cache {
+      request.cache = cache;
+    }
+    if let Some(redirect) = init.redirect {
+      request.redirect = redirect;
+    }
+    if let Some(referrer) = init.referrer {
+      request.referrer = if referrer.is_empty() {
+        String::new()
+      } else {
+        URL::new(&referrer).get_href()
+      };
+    }
+    if init.referrer_policy.is_some() {
+      request.referrer_policy = init.referrer_policy;
+    }
+    if let Some(integrity) = init.integrity {
+      request.integrity = integrity;
+    }
+    if let Some(keepalive) = init.keepalive {
+      request.keepalive = keepalive;
+    }
+    if let Some(signal) = init.signal {
+      request.signal = signal;
+    }
+    if init.max_redirects.is_some() {
+      request.max_redirects = init.max_redirects;
+    }
+    if init.redirect_header_filter.is_some() {
+      request.redirect_header_filter = init.redirect_header_filter;
+    }
+    if let Some(browser_compatible) = init.browser_compatible {
+      request.browser_compatible = browser_compatible;
+    }
+    if init.timeout.is_some() {
+      request.timeout = init.timeout;
+    }
+    if init.duplex.is_some() {
+      request.duplex = init.duplex;
+    }
+    if let Some(allow_file_urls) = init.allow_file_urls {
+      request.allow_file_urls = allow_file_urls;
+    }
+    #[cfg(feature = "tls")]
+    if init.tls.is_some() {
+      request.tls = init.tls;
+    }
+    #[cfg(feature = "tokio-fetch")]
+    if init.pool.is_some() {
+      request.pool = init.pool;
+    }
+    request.validate()?;
+    Ok(request)
+  }
+
+  fn from_url(url: URL) -> Self {
+    Self {
+      method: Method::GET,
+      url,
+      headers: HashMap::new(),
+      body: None,
+      body_used: false,
+      mode: RequestMode::default(),
+      credentials: RequestCredentials::default(),
+      cache: RequestCache::default(),
+      redirect: RequestRedirect::default(),
+      referrer: "about:client".to_string(),
+      referrer_policy: None,
+      integrity: String::new(),
+      keepalive: false,
+      signal: AbortSignal::new(),
+      max_redirects: None,
+      redirect_header_filter: None,
+      browser_compatible: false,
+      timeout: None,
+      duplex: None,
+      allow_file_urls: false,
+      #[cfg(feature = "tls")]
+      tls: None,
+      #[cfg(feature = "tokio-fetch")]
+      pool: None,
+    }
+  }
+
+  fn validate(&self) -> Result<(), RequestError> {
+    validate_headers(&self.headers).map_err(|e| RequestError::Invalid(e.to_string()))?;
+    validate_framing(&self.headers, self.body.as_ref()).map_err(|e| RequestError::Invalid(e.to_string()))?;
+    validate_options(&self.method, self.body.as_ref(), self.duplex, self.mode, self.cache, self.browser_compatible)
+      .map_err(|e| RequestError::Invalid(e.to_string()))
+  }
+}
+
+impl Request {
+  pub fn method(&self) -> &Method {
+    &self.method
+  }
+
+  pub fn url(&self) -> &URL {
+    &self.url
+  }
+
+  pub fn headers(&self) -> &HashMap<String, String> {
+    &self.headers
+  }
+
+  pub fn headers_mut(&mut self) -> &mut HashMap<String, String> {
+    &mut self.headers
+  }
+
+  pub fn mode(&self) -> RequestMode {
+    self.mode
+  }
+
+  pub fn credentials(&self) -> RequestCredentials {
+    self.credentials
+  }
+
+  pub fn cache(&self) -> RequestCache {
+    self.cache
+  }
+
+  pub fn redirect(&self) -> RequestRedirect {
+    self.redirect
+  }
+
+  /// 请求的referrer，`about:client`表示默认值，空字符串表示不发送
+  pub fn referrer(&self) -> String {
+    self.referrer.clone()
+  }
+
+  pub fn referrer_policy(&self) -> Option<ReferrerPolicy> {
+    self.referrer_policy
+  }
+
+  pub fn integrity(&self) -> String {
+    self.integrity.clone()
+  }
+
+  pub fn keepalive(&self) -> bool {
+    self.keepalive
+  }
+
+  pub fn signal(&self) -> &AbortSignal {
+    &self.signal
+  }
+
+  pub fn max_redirects(&self) -> Option<usize> {
+    self.max_redirects
+  }
+
+  pub fn redirect_header_filter(&self) -> Option<&RedirectHeaderFilter> {
+    self.redirect_header_filter.as_ref()
+  }
+
+  pub fn timeout(&self) -> Option<Duration> {
+    self.timeout
+  }
+
+  pub fn duplex(&self) -> Option<RequestDuplex> {
+    self.duplex
+  }
+
+  pub fn allow_file_urls(&self) -> bool {
+    self.allow_file_urls
+  }
+
+  #[cfg(feature = "tls")]
+  pub fn tls(&self) -> Option<&crate::TlsOptions> {
+    self.tls.as_ref()
+  }
+
+  #[cfg(feature = "tokio-fetch")]
+  pub fn pool(&self) -> Option<&crate::ConnectionPool> {
+    self.pool.as_ref()
+  }
+
+  pub fn body_used(&self) -> bool {
+    self.body_used
+  }
+}
+
+impl Request {
+  /// 读取请求正文，正文只能被读取一次
+  pub async fn text(&mut self) -> Result<String, RequestError> {
+    Ok(String::from_utf8_lossy(&self.bytes().await?).into_owned())
+  }
+
+  pub async fn bytes(&mut self) -> Result<Bytes, RequestError> {
+    match self.take_body()? {
+      Some(body) => body.into_bytes().await.map_err(RequestError::Body),
+      None => Ok(Bytes::new()),
+    }
+  }
+
+  pub async fn array_buffer(&mut self) -> Result<Vec<u8>, RequestError> {
+    Ok(self.bytes().await?.to_vec())
+  }
+
+  /// 读取请求正文为`Blob`，类型取自`Content-Type`请求头
+  pub async fn blob(&mut self) -> Result<Blob, RequestError> {
+    let body = self.bytes().await?;
+    Ok(Blob::new(body, self.content_type().unwrap_or_default()))
+  }
+
+  /// 按`Content-Type`请求头将正文解析为`FormData`
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::request::Request;
+  /// use fetch_js::request_init::{Method, RequestInit};
+  ///
+  /// let init = RequestInit {
+  ///   method: Some(Method::POST),
+  ///   headers: Some([("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string())].into()),
+  ///   body: Some("a=1&b=x+y&a=2".into()),
+  ///   ..Default::default()
+  /// };
+  /// let mut request = Request::new("http://example.com", init).unwrap();
+  /// # futures_util::FutureExt::now_or_never(async {
+  /// let form = request.form_data().await.unwrap();
+  /// assert_eq!(form.get_all("a").len(), 2);
+  /// assert_eq!(form.get("b").unwrap().as_text(), Some("x y"));
+  /// # }).unwrap();
+  /// ```
+  pub async fn form_data(&mut self) -> Result<FormData, RequestError> {
+    self.form_data_with_limits(MultipartLimits::default()).await
+  }
+
+  /// 与`form_data`相同，使用指定的字段数和字段大小限制
+  pub async fn form_data_with_limits(&mut self, limits: MultipartLimits) -> Result<FormData, RequestError> {
+    let body = self.take_body()?.map(BodyInit::into_stream).unwrap_or_else(ReadableStream::empty);
+    let content_type = self.content_type().map(String::from);
+    Ok(form_data::parse(body, content_type.as_deref(), limits).await?)
+  }
+
+  fn content_type(&self) -> Option<&str> {
+    self.headers.iter()
+      .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+      .map(|(_, v)| v.as_str())
+  }
+
+  /// 取出请求正文的流，之后正文被视为已读取
+  pub fn body(&mut self) -> Result<Option<ReadableStream<Bytes>>, RequestError> {
+    Ok(self.take_body()?.map(BodyInit::into_stream))
+  }
+
+  /// 取出请求正文并标记为已使用
+  pub(crate) fn take_body(&mut self) -> Result<Option<BodyInit>, RequestError> {
+    if self.body_used {
+      return Err(RequestError::BodyUsed);
+    }
+    self.body_used = self.body.is_some();
+    Ok(self.body.take())
+  }
+
+  /// 复制请求，正文已被读取时返回错误；流式正文会被分成两个流，分别留给原请求和副本
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::request::Request;
+  /// use fetch_js::request_init::{Method, RequestInit};
+  ///
+  /// let mut request = Request::new("http://example.com", RequestInit {
+  ///   method: Some(Method::PUT),
+  ///   body: Some("data".into()),
+  ///   ..Default::default()
+  /// }).unwrap();
+  /// let copy = request.clone().unwrap();
+  /// assert_eq!(copy.method(), &Method::PUT);
+  /// assert_eq!(copy.body_used(), false);
+  /// ```
+  #[allow(clippy::should_implement_trait)]
+  pub fn clone(&mut self) -> Result<Self, RequestError> {
+    if self.body_used {
+      return Err(RequestError::BodyUsed);
+    }
+    let body = match self.body.take() {
+      Some(BodyInit::Stream { stream, length }) => {
+        let (stream, copy) = stream.tee();
+        self.body = Some(BodyInit::Stream { stream, length });
+        Some(BodyInit::Stream { stream: copy, length })
+      },
+      Some(body) => {
+        let copy = body.try_clone();
+        self.body = Some(body);
+        Some(copy.ok_or_else(|| RequestError::Invalid("failed to clone the request body".to_string()))?)
+      },
+      None => None,
+    };
+    Ok(Self {
+      method: self.method.clone(),
+      url: self.url.clone(),
+      headers: self.headers.clone(),
+      body,
+      body_used: false,
+      mode: self.mode,
+      credentials: self.credentials,
+      cache: self.cache,
+      redirect: self.redirect,
+      referrer: self.referrer.clone(),
+      referrer_policy: self.referrer_policy,
+      integrity: self.integrity.clone(),
+      keepalive: self.keepalive,
+      signal: self.signal.clone(),
+      max_redirects: self.max_redirects,
+      redirect_header_filter: self.redirect_header_filter.clone(),
+      browser_compatible: self.browser_compatible,
+      timeout: self.timeout,
+      duplex: self.duplex,
+      allow_file_urls: self.allow_file_urls,
+      #[cfg(feature = "tls")]
+      tls: self.tls.clone(),
+      #[cfg(feature = "tokio-fetch")]
+      pool: self.pool.clone(),
+    })
+  }
+}
+
+impl std::fmt::Debug for Request {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Request")
+      .field("method", &self.method)
+      .field("url", &self.url.get_href())
+      .field("headers", &self.headers)
+      .field("body_used", &self.body_used)
+      .field("mode", &self.mode)
+      .field("credentials", &self.credentials)
+      .field("cache", &self.cache)
+      .field("redirect", &self.redirect)
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reject_invalid_init() {
+    let init = RequestInit {
+      mode: Some(RequestMode::NoCors),
+      method: Some(Method::PUT),
+      ..Default::default()
+    };
+    assert!(matches!(Request::new("http://example.com", init), Err(RequestError::Invalid(_))));
+    let init = RequestInit {
+      body: Some("data".into()),
+      ..Default::default()
+    };
+    assert!(matches!(Request::new("http://example.com", init), Err(RequestError::Invalid(_))));
+    let init = RequestInit {
+      method: Some(Method::TRACE),
+      browser_compatible: Some(true),
+      ..Default::default()
+    };
+    assert!(matches!(Request::new("http://example.com", init), Err(RequestError::Invalid(_))));
+  }
+
+  #[test]
+  fn init_overrides_request() {
+    let request = Request::new("http://example.com", RequestInit {
+      headers: Some(HashMap::from([("X-A".to_string(), "1".to_string())])),
+      redirect: Some(RequestRedirect::Manual),
+      ..Default::default()
+    }).unwrap();
+    let request = Request::new(request, RequestInit {
+      redirect: Some(RequestRedirect::Error),
+      ..Default::default()
+    }).unwrap();
+    assert_eq!(request.redirect(), RequestRedirect::Error);
+    assert_eq!(request.headers().get("X-A").unwrap(), "1");
+    assert_eq!(request.referrer(), "about:client");
+  }
+
+  #[test]
+  fn explicit_get_and_empty_headers_override() {
+    let request = Request::new("http://example.com", RequestInit {
+      method: Some(Method::PUT),
+      headers: Some(HashMap::from([("X-A".to_string(), "1".to_string())])),
+      ..Default::default()
+    }).unwrap();
+    let request = Request::new(request, RequestInit {
+      method: Some(Method::GET),
+      headers: Some(HashMap::new()),
+      ..Default::default()
+    }).unwrap();
+    assert_eq!(request.method(), &Method::GET);
+    assert!(request.headers().is_empty());
+  }
+
+  #[test]
+  fn clone_stream_body() {
+    let chunks = vec![Ok(Bytes::from("a")), Ok(Bytes::from("b"))];
+    let stream = ReadableStream::from_stream(futures_util::stream::iter(chunks));
+    let mut request = Request::new("http://example.com", RequestInit {
+      method: Some(Method::POST),
+      body: Some(BodyInit::from_stream(stream).with_length(2)),
+      ..Default::default()
+    }).unwrap();
+    let mut copy = request.clone().unwrap();
+    futures_util::FutureExt::now_or_never(async {
+      assert_eq!(copy.text().await.unwrap(), "ab");
+      assert_eq!(request.text().await.unwrap(), "ab");
+    }).unwrap();
+    assert!(matches!(request.clone(), Err(RequestError::BodyUsed)));
+  }
+
+  #[cfg(feature = "tokio-fetch")]
+  #[tokio::test]
+  async fn clone_file_body() {
+    let path = std::env::temp_dir().join(format!("fetch-js-clone-{}.txt", std::process::id()));
+    let content = "0123456789".repeat(20_000);
+    std::fs::write(&path, &content).unwrap();
+    let body = BodyInit::from_file(&path).await.unwrap().with_range(5..150_005);
+    let mut request = Request::new("http://example.com", RequestInit {
+      method: Some(Method::PUT),
+      body: Some(body),
+      ..Default::default()
+    }).unwrap();
+    let mut copy = request.clone().unwrap();
+    // 两个副本共享同一个文件句柄，同时读取也不会互相影响
+    let (body, copied) = tokio::join!(request.bytes(), copy.bytes());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(body.unwrap(), &content.as_bytes()[5..150_005]);
+    assert_eq!(copied.unwrap(), &content.as_bytes()[5..150_005]);
+  }
+}
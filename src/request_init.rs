@@ -1,63 +1,153 @@
+mod body;
+mod builder;
+mod method;
+mod options;
+
+pub use body::*;
+pub use builder::*;
+pub use method::*;
+pub use options::*;
+
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use crate::abort_controller::AbortSignal;
+use crate::url::URL;
 
-pub enum Method {
-  GET,
-  POST,
-  PUT,
-  DELETE,
-  PATCH,
-  HEAD,
-  OPTIONS,
-}
+/// 跨源重定向时决定请求头是否保留的回调，参数依次为请求头名称、重定向前和重定向后的URL
+pub type RedirectHeaderFilter = Arc<dyn Fn(&str, &URL, &URL) -> bool + Send + Sync>;
 
-pub(crate) fn get_method_string(method: &Method) -> String {
-  match method {
-    Method::GET => "GET".to_string(),
-    Method::POST => "POST".to_string(),
-    Method::PUT => "PUT".to_string(),
-    Method::DELETE => "DELETE".to_string(),
-    Method::PATCH => "PATCH".to_string(),
-    Method::HEAD => "HEAD".to_string(),
-    Method::OPTIONS => "OPTIONS".to_string(),
-  }
-}
+const SENSITIVE_HEADERS: [&str; 3] = ["Authorization", "Cookie", "Proxy-Authorization"];
 
+#[cfg_attr(not(feature = "tokio-fetch"), allow(dead_code))]
 pub(crate) fn header_sort(headers: &HashMap<String, String>) -> Vec<String> {
   let sorted_headers = headers.iter().collect::<Vec<(&String, &String)>>();
   sorted_headers.iter().map(|(k, v)| format!("{}: {}", k, v)).collect()
 }
 
+/// 不区分大小写地判断请求头是否存在
+pub(crate) fn has_header(headers: &HashMap<String, String>, name: &str) -> bool {
+  headers.keys().any(|k| k.eq_ignore_ascii_case(name))
+}
+
+/// 判断请求头是否携带凭据，这类请求头默认不会跟随跨源重定向
+///
+/// # Example
+/// ```
+/// use fetch_js::request_init::is_sensitive_header;
+/// assert!(is_sensitive_header("authorization"));
+/// assert!(!is_sensitive_header("Accept"));
+/// ```
+pub fn is_sensitive_header(name: &str) -> bool {
+  SENSITIVE_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name))
+}
+
+/// 是否为RFC 9110定义的token，用于校验方法名、请求头名称和MIME类型
+pub(crate) fn is_token(s: &str) -> bool {
+  !s.is_empty() && s.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// 校验请求头名称是否为合法的token，值中是否含有换行等非法字符
+pub(crate) fn validate_headers(headers: &HashMap<String, String>) -> Result<(), RequestInitError> {
+  for (name, value) in headers {
+    if !is_token(name) {
+      return Err(RequestInitError::InvalidHeaderName(name.clone()));
+    }
+    if value.bytes().any(|b| b == b'\r' || b == b'\n' || b == 0) {
+      return Err(RequestInitError::InvalidHeaderValue(name.clone()));
+    }
+  }
+  Ok(())
+}
+
+/// 正文的长度由`fetch`决定：不允许自行设置`Transfer-Encoding`，
+/// 长度未知的流使用chunked编码，不能同时设置`Content-Length`
+pub(crate) fn validate_framing(headers: &HashMap<String, String>, body: Option<&BodyInit>) -> Result<(), RequestInitError> {
+  if has_header(headers, "Transfer-Encoding") {
+    return Err(RequestInitError::Conflict("Transfer-Encoding header cannot be set".to_string()));
+  }
+  if body.is_some_and(|body| body.len().is_none()) && has_header(headers, "Content-Length") {
+    return Err(RequestInitError::Conflict(
+      "Content-Length header cannot be set for a stream of unknown length, use BodyInit::with_length".to_string(),
+    ));
+  }
+  Ok(())
+}
+
+/// 校验方法、正文和各选项之间是否冲突
+pub(crate) fn validate_options(
+  method: &Method,
+  body: Option<&BodyInit>,
+  duplex: Option<RequestDuplex>,
+  mode: RequestMode,
+  cache: RequestCache,
+  browser_compatible: bool,
+) -> Result<(), RequestInitError> {
+  if !method.is_valid() {
+    return Err(RequestInitError::Conflict(format!("'{}' is not a valid HTTP method", method)));
+  }
+  if browser_compatible && method.is_forbidden() {
+    return Err(RequestInitError::Conflict(format!("'{}' HTTP method is unsupported", method)));
+  }
+  if mode == RequestMode::Navigate {
+    return Err(RequestInitError::Conflict("mode 'navigate' cannot be used to construct a request".to_string()));
+  }
+  if cache == RequestCache::OnlyIfCached && mode != RequestMode::SameOrigin {
+    return Err(RequestInitError::Conflict("cache 'only-if-cached' can be set only with 'same-origin' mode".to_string()));
+  }
+  if mode == RequestMode::NoCors && !matches!(method, Method::GET | Method::HEAD | Method::POST) {
+    return Err(RequestInitError::Conflict(format!("'{}' is unsupported in no-cors mode", method)));
+  }
+  if body.is_some() && matches!(method, Method::GET | Method::HEAD) {
+    return Err(RequestInitError::Conflict("request with GET/HEAD method cannot have body".to_string()));
+  }
+  if browser_compatible && body.is_some_and(BodyInit::is_stream) && duplex.is_none() {
+    return Err(RequestInitError::Conflict("duplex option is required when sending a streaming body".to_string()));
+  }
+  Ok(())
+}
+
+#[derive(Default)]
 pub struct RequestInit {
-  pub method: Method,
-  pub headers: HashMap<String, String>,
-  pub body: Option<String>,
-  pub mode: Option<String>,
-  pub credentials: Option<String>,
-  pub cache: Option<String>,
-  pub redirect: Option<String>,
+  /// 未设置时沿用`input`请求的方法，新请求默认为`GET`
+  pub method: Option<Method>,
+  /// 设置后替换`input`请求的全部请求头，设为空表时清空
+  pub headers: Option<HashMap<String, String>>,
+  pub body: Option<BodyInit>,
+  pub mode: Option<RequestMode>,
+  pub credentials: Option<RequestCredentials>,
+  pub cache: Option<RequestCache>,
+  pub redirect: Option<RequestRedirect>,
   pub referrer: Option<String>,
   pub integrity: Option<String>,
+  /// 为`true`时保持连接，响应正文读完后连接放回连接池供之后的请求复用
   pub keepalive: Option<bool>,
   pub signal: Option<AbortSignal>,
-  pub referrer_policy: Option<String>,
-}
-
-impl Default for RequestInit {
-  fn default() -> Self {
-    RequestInit {
-      method: Method::GET,
-      headers: HashMap::new(),
-      body: None,
-      mode: None,
-      credentials: None,
-      cache: None,
-      redirect: None,
-      referrer: None,
-      integrity: None,
-      keepalive: None,
-      signal: None,
-      referrer_policy: None,
-    }
+  pub referrer_policy: Option<ReferrerPolicy>,
+  /// `redirect`为`follow`时最多跟随的重定向次数，默认为20
+  pub max_redirects: Option<usize>,
+  /// 重定向跨源或从https降级到http时，对每个请求头调用以决定是否保留；
+  /// 未设置时移除`is_sensitive_header`判定的请求头
+  pub redirect_header_filter: Option<RedirectHeaderFilter>,
+  /// 按浏览器的限制校验请求，例如拒绝`CONNECT`、`TRACE`和`TRACK`方法
+  pub browser_compatible: Option<bool>,
+  /// 整个请求（包括跟随重定向）允许的最长时间
+  pub timeout: Option<Duration>,
+  /// 流式正文发送完毕后才开始读取响应；`browser_compatible`时发送流式正文必须设置
+  pub duplex: Option<RequestDuplex>,
+  /// 允许`fetch`读取`file:` URL指向的本地文件，默认不允许
+  pub allow_file_urls: Option<bool>,
+  /// 连接`https:` URL时使用的TLS配置，未设置时信任系统的根证书，系统中没有可用的证书时使用webpki-roots内置的根证书
+  #[cfg(feature = "tls")]
+  pub tls: Option<crate::TlsOptions>,
+  /// `keepalive`请求使用的连接池，未设置时使用`ConnectionPool::global()`
+  #[cfg(feature = "tokio-fetch")]
+  pub pool: Option<crate::ConnectionPool>,
+}
+
+impl RequestInit {
+  /// 创建`RequestInit`的构建器
+  pub fn builder() -> RequestInitBuilder {
+    RequestInitBuilder::new()
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,326 @@
+use std::io;
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use crate::blob::{Blob, File};
+use crate::form_data::{self, FormData};
+use crate::streams::ReadableStream;
+
+/// 请求正文，对应JS中的`BodyInit`
+///
+/// 字符串和字节会一次性发送；流式正文按需读取，`length`为`None`时使用chunked编码上传；
+/// 文件正文从`offset`开始发送`length`字节，在Linux的明文连接上使用`sendfile`零拷贝发送；
+/// 表单以`multipart/form-data`格式边编码边发送；`Blob`以其类型作为默认的`Content-Type`
+///
+/// # Example
+/// ```
+/// use fetch_js::request_init::BodyInit;
+/// use fetch_js::streams::ReadableStream;
+///
+/// let body = BodyInit::from("hello");
+/// assert_eq!(body.len(), Some(5));
+///
+/// let chunks = vec![Ok(bytes::Bytes::from("a")), Ok(bytes::Bytes::from("b"))];
+/// let body = BodyInit::from_stream(ReadableStream::from_stream(futures_util::stream::iter(chunks)));
+/// assert!(body.is_stream());
+/// assert_eq!(body.len(), None);
+/// assert_eq!(body.with_length(2).len(), Some(2));
+/// ```
+pub enum BodyInit {
+  Text(String),
+  Bytes(Bytes),
+  Stream {
+    stream: ReadableStream<Bytes>,
+    length: Option<u64>,
+  },
+  FormData {
+    form_data: FormData,
+    boundary: String,
+  },
+  Blob(Blob),
+  #[cfg(feature = "tokio-fetch")]
+  File {
+    file: std::fs::File,
+    offset: u64,
+    length: u64,
+    content_type: Option<String>,
+  },
+}
+
+impl BodyInit {
+  /// 由字节流创建，长度未知
+  pub fn from_stream(stream: ReadableStream<Bytes>) -> Self {
+    BodyInit::Stream { stream, length: None }
+  }
+
+  /// 由`tokio::io::AsyncRead`创建，数据在发送时才会被读取
+  #[cfg(feature = "tokio-fetch")]
+  pub fn from_reader<R>(reader: R) -> Self
+  where
+    R: tokio::io::AsyncRead + Send + 'static,
+  {
+    Self::from_stream(ReadableStream::from_stream(tokio_util::io::ReaderStream::new(reader)))
+  }
+
+  /// 打开文件作为正文，长度取自文件元数据，`Content-Type`根据扩展名推断
+  ///
+  /// # Example
+  /// ```no_run
+  /// use fetch_js::fetch;
+  /// use fetch_js::request_init::{BodyInit, Method, RequestInit};
+  ///
+  /// #[tokio::main]
+  /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+  ///   // 只上传文件的前1MiB
+  ///   let body = BodyInit::from_file("artifact.tar.gz").await?.with_range(0..1024 * 1024);
+  ///   let init = RequestInit::builder().method(Method::PUT).body(body).build()?;
+  ///   fetch("http://example.com/upload", init).await?;
+  ///   Ok(())
+  /// }
+  /// ```
+  #[cfg(feature = "tokio-fetch")]
+  pub async fn from_file(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+    let path = path.as_ref();
+    let file = tokio::fs::File::open(path).await?;
+    let metadata = file.metadata().await?;
+    if !metadata.is_file() {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file", path.display())));
+    }
+    Ok(BodyInit::File {
+      file: file.into_std().await,
+      offset: 0,
+      length: metadata.len(),
+      content_type: crate::mime::from_path(path).map(String::from),
+    })
+  }
+
+  /// 只发送文件正文中的一段，范围相对于当前的范围并会被限制在其以内；对其他正文没有作用
+  pub fn with_range(self, range: std::ops::Range<u64>) -> Self {
+    match self {
+      #[cfg(feature = "tokio-fetch")]
+      BodyInit::File { file, offset, length, content_type } => {
+        let end = range.end.min(length);
+        let start = range.start.min(end);
+        BodyInit::File { file, offset: offset + start, length: end - start, content_type }
+      },
+      body => {
+        let _ = range;
+        body
+      },
+    }
+  }
+
+  /// 指定流式正文的长度，发送时使用`Content-Length`而不是chunked编码，
+  /// 实际长度不符时请求失败；对非流式正文没有作用
+  pub fn with_length(self, length: u64) -> Self {
+    match self {
+      BodyInit::Stream { stream, .. } => BodyInit::Stream { stream, length: Some(length) },
+      body => body,
+    }
+  }
+
+  /// 正文的长度，长度未知的流返回`None`
+  #[allow(clippy::len_without_is_empty)]
+  pub fn len(&self) -> Option<u64> {
+    match self {
+      BodyInit::Text(text) => Some(text.len() as u64),
+      BodyInit::Bytes(bytes) => Some(bytes.len() as u64),
+      BodyInit::Stream { length, .. } => *length,
+      BodyInit::FormData { form_data, boundary } => Some(form_data::encoded_length(form_data, boundary)),
+      BodyInit::Blob(blob) => Some(blob.size()),
+      #[cfg(feature = "tokio-fetch")]
+      BodyInit::File { length, .. } => Some(*length),
+    }
+  }
+
+  /// 由正文推断的`Content-Type`，请求中没有设置时使用
+  pub fn content_type(&self) -> Option<String> {
+    match self {
+      BodyInit::FormData { boundary, .. } => Some(format!("multipart/form-data; boundary={}", boundary)),
+      BodyInit::Blob(blob) => Some(blob.get_type()).filter(|content_type| !content_type.is_empty()),
+      #[cfg(feature = "tokio-fetch")]
+      BodyInit::File { content_type, .. } => content_type.clone(),
+      _ => None,
+    }
+  }
+
+  pub fn is_stream(&self) -> bool {
+    matches!(self, BodyInit::Stream { .. })
+  }
+
+  /// 复制非流式正文，流只能被读取一次
+  pub(crate) fn try_clone(&self) -> Option<Self> {
+    match self {
+      BodyInit::Text(text) => Some(BodyInit::Text(text.clone())),
+      BodyInit::Bytes(bytes) => Some(BodyInit::Bytes(bytes.clone())),
+      BodyInit::Stream { .. } => None,
+      BodyInit::FormData { form_data, boundary } => Some(BodyInit::FormData {
+        form_data: form_data.clone(),
+        boundary: boundary.clone(),
+      }),
+      BodyInit::Blob(blob) => Some(BodyInit::Blob(blob.clone())),
+      #[cfg(feature = "tokio-fetch")]
+      BodyInit::File { file, offset, length, content_type } => Some(BodyInit::File {
+        file: file.try_clone().ok()?,
+        offset: *offset,
+        length: *length,
+        content_type: content_type.clone(),
+      }),
+    }
+  }
+
+  /// 转换为字节流
+  pub(crate) fn into_stream(self) -> ReadableStream<Bytes> {
+    let bytes = match self {
+      BodyInit::Text(text) => Bytes::from(text),
+      BodyInit::Bytes(bytes) => bytes,
+      BodyInit::Stream { stream, .. } => return stream,
+      BodyInit::FormData { form_data, boundary } => return form_data::encode(&form_data, &boundary),
+      BodyInit::Blob(blob) => return blob.stream(),
+      #[cfg(feature = "tokio-fetch")]
+      BodyInit::File { file, offset, length, .. } => return file_stream(file, offset, length),
+    };
+    ReadableStream::from_stream(futures_util::stream::iter([Ok(bytes)]))
+  }
+
+  /// 读取完整的正文
+  pub(crate) async fn into_bytes(self) -> io::Result<Bytes> {
+    let mut stream = match self {
+      BodyInit::Text(text) => return Ok(Bytes::from(text)),
+      BodyInit::Bytes(bytes) => return Ok(bytes),
+      body => body.into_stream(),
+    };
+    let mut body = BytesMut::new();
+    while let Some(chunk) = stream.next().await {
+      body.extend_from_slice(&chunk?);
+    }
+    Ok(body.freeze())
+  }
+}
+
+/// 从文件的`offset`处读取`length`字节
+///
+/// 使用不改变文件读写位置的读取，`try_clone`得到的句柄共享读写位置，重定向重发或复制的请求可能同时读取同一个文件
+#[cfg(feature = "tokio-fetch")]
+fn file_stream(file: std::fs::File, offset: u64, length: u64) -> ReadableStream<Bytes> {
+  const CHUNK_SIZE: u64 = 64 * 1024;
+
+  let file = std::sync::Arc::new(file);
+  ReadableStream::from_stream(futures_util::stream::try_unfold((offset, length), move |(offset, remaining)| {
+    let file = file.clone();
+    async move {
+      if remaining == 0 {
+        return Ok(None);
+      }
+      let size = remaining.min(CHUNK_SIZE) as usize;
+      let chunk = tokio::task::spawn_blocking(move || read_at(&file, offset, size)).await.map_err(io::Error::other)??;
+      // 文件变短时提前结束，由发送方检查长度
+      if chunk.is_empty() {
+        return Ok(None);
+      }
+      let read = chunk.len() as u64;
+      Ok(Some((chunk, (offset + read, remaining - read))))
+    }
+  }))
+}
+
+#[cfg(feature = "tokio-fetch")]
+fn read_at(file: &std::fs::File, offset: u64, size: usize) -> io::Result<Bytes> {
+  let mut buffer = vec![0; size];
+  let read = loop {
+    #[cfg(unix)]
+    let result = std::os::unix::fs::FileExt::read_at(file, &mut buffer, offset);
+    #[cfg(windows)]
+    let result = std::os::windows::fs::FileExt::seek_read(file, &mut buffer, offset);
+    #[cfg(not(any(unix, windows)))]
+    let result = seek_read(file, &mut buffer, offset);
+    match result {
+      Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+      result => break result?,
+    }
+  };
+  buffer.truncate(read);
+  Ok(Bytes::from(buffer))
+}
+
+/// 没有定位读取的平台上先移动读写位置再读取；克隆的句柄共享读写位置，用锁让两步不被其他读取打断
+#[cfg(all(feature = "tokio-fetch", not(any(unix, windows))))]
+fn seek_read(file: &std::fs::File, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+  use std::io::{Read, Seek};
+  static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+  let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+  let mut file = file.try_clone()?;
+  file.seek(io::SeekFrom::Start(offset))?;
+  file.read(buffer)
+}
+
+impl From<String> for BodyInit {
+  fn from(text: String) -> Self {
+    BodyInit::Text(text)
+  }
+}
+
+impl From<&str> for BodyInit {
+  fn from(text: &str) -> Self {
+    BodyInit::Text(text.to_string())
+  }
+}
+
+impl From<Bytes> for BodyInit {
+  fn from(bytes: Bytes) -> Self {
+    BodyInit::Bytes(bytes)
+  }
+}
+
+impl From<Vec<u8>> for BodyInit {
+  fn from(bytes: Vec<u8>) -> Self {
+    BodyInit::Bytes(Bytes::from(bytes))
+  }
+}
+
+impl From<FormData> for BodyInit {
+  fn from(form_data: FormData) -> Self {
+    BodyInit::FormData {
+      form_data,
+      boundary: form_data::boundary(),
+    }
+  }
+}
+
+impl From<Blob> for BodyInit {
+  fn from(blob: Blob) -> Self {
+    BodyInit::Blob(blob)
+  }
+}
+
+impl From<File> for BodyInit {
+  fn from(file: File) -> Self {
+    BodyInit::Blob(file.into())
+  }
+}
+
+impl From<ReadableStream<Bytes>> for BodyInit {
+  fn from(stream: ReadableStream<Bytes>) -> Self {
+    Self::from_stream(stream)
+  }
+}
+
+impl std::fmt::Debug for BodyInit {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      BodyInit::Text(text) => f.debug_tuple("Text").field(text).finish(),
+      BodyInit::Bytes(bytes) => f.debug_tuple("Bytes").field(bytes).finish(),
+      BodyInit::Stream { length, .. } => f.debug_struct("Stream").field("length", length).finish_non_exhaustive(),
+      BodyInit::FormData { form_data, boundary } => f.debug_struct("FormData")
+        .field("form_data", form_data)
+        .field("boundary", boundary)
+        .finish(),
+      BodyInit::Blob(blob) => f.debug_tuple("Blob").field(blob).finish(),
+      #[cfg(feature = "tokio-fetch")]
+      BodyInit::File { offset, length, content_type, .. } => f.debug_struct("File")
+        .field("offset", offset)
+        .field("length", length)
+        .field("content_type", content_type)
+        .finish_non_exhaustive(),
+    }
+  }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+use crate::abort_controller::AbortSignal;
+use crate::url::URLSearchParams;
+use super::*;
+
+/// 构建`RequestInit`失败时返回的错误
+#[derive(Debug, Error, PartialEq)]
+pub enum RequestInitError {
+  #[error("invalid header name: {0}")]
+  InvalidHeaderName(String),
+  #[error("invalid value for header {0}")]
+  InvalidHeaderValue(String),
+  #[error("{0}")]
+  Conflict(String),
+  #[error("failed to serialize body: {0}")]
+  Serialize(String),
+}
+
+/// `RequestInit`的构建器，错误会在`build`时统一返回
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use fetch_js::request_init::{Method, RequestInit};
+///
+/// let init = RequestInit::builder()
+///   .method(Method::POST)
+///   .header("X-Request-Id", "42")
+///   .body("hello")
+///   .timeout(Duration::from_secs(5))
+///   .build()
+///   .unwrap();
+/// assert_eq!(init.method, Some(Method::POST));
+/// assert_eq!(init.headers.unwrap().get("X-Request-Id").unwrap(), "42");
+///
+/// assert!(RequestInit::builder().header("Bad Header", "x").build().is_err());
+/// assert!(RequestInit::builder().method(Method::GET).body("x").build().is_err());
+/// ```
+#[derive(Default)]
+pub struct RequestInitBuilder {
+  init: RequestInit,
+  error: Option<RequestInitError>,
+}
+
+impl RequestInitBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn method(mut self, method: Method) -> Self {
+    self.init.method = Some(method);
+    self
+  }
+
+  /// 设置请求头，同名（不区分大小写）的旧值会被替换
+  pub fn header(mut self, name: &str, value: &str) -> Self {
+    let headers = self.init.headers.get_or_insert_with(HashMap::new);
+    headers.retain(|k, _| !k.eq_ignore_ascii_case(name));
+    headers.insert(name.to_string(), value.to_string());
+    self
+  }
+
+  /// 逐个设置请求头；传入空表时也会覆盖`input`请求的请求头
+  pub fn headers(mut self, headers: HashMap<String, String>) -> Self {
+    self.init.headers.get_or_insert_with(HashMap::new);
+    for (name, value) in headers {
+      self = self.header(&name, &value);
+    }
+    self
+  }
+
+  pub fn body(mut self, body: impl Into<BodyInit>) -> Self {
+    self.init.body = Some(body.into());
+    self
+  }
+
+  /// 以`application/x-www-form-urlencoded`格式设置正文
+  pub fn form(self, params: &URLSearchParams) -> Self {
+    self.default_header("Content-Type", "application/x-www-form-urlencoded;charset=UTF-8")
+      .body(params.to_string())
+  }
+
+  /// 将值序列化为JSON作为正文，并在未设置时添加`Content-Type: application/json`
+  #[cfg(feature = "json")]
+  pub fn json<T: serde::Serialize + ?Sized>(mut self, value: &T) -> Self {
+    match serde_json::to_string(value) {
+      Ok(body) => self.default_header("Content-Type", "application/json").body(body),
+      Err(e) => {
+        self.error.get_or_insert(RequestInitError::Serialize(e.to_string()));
+        self
+      },
+    }
+  }
+
+  pub fn mode(mut self, mode: RequestMode) -> Self {
+    self.init.mode = Some(mode);
+    self
+  }
+
+  pub fn credentials(mut self, credentials: RequestCredentials) -> Self {
+    self.init.credentials = Some(credentials);
+    self
+  }
+
+  pub fn cache(mut self, cache: RequestCache) -> Self {
+    self.init.cache = Some(cache);
+    self
+  }
+
+  pub fn redirect(mut self, redirect: RequestRedirect) -> Self {
+    self.init.redirect = Some(redirect);
+    self
+  }
+
+  pub fn referrer(mut self, referrer: &str) -> Self {
+    self.init.referrer = Some(referrer.to_string());
+    self
+  }
+
+  pub fn referrer_policy(mut self, referrer_policy: ReferrerPolicy) -> Self {
+    self.init.referrer_policy = Some(referrer_policy);
+    self
+  }
+
+  pub fn integrity(mut self, integrity: &str) -> Self {
+    self.init.integrity = Some(integrity.to_string());
+    self
+  }
+
+  pub fn keepalive(mut self, keepalive: bool) -> Self {
+    self.init.keepalive = Some(keepalive);
+    self
+  }
+
+  pub fn signal(mut self, signal: AbortSignal) -> Self {
+    self.init.signal = Some(signal);
+    self
+  }
+
+  pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+    self.init.max_redirects = Some(max_redirects);
+    self
+  }
+
+  pub fn redirect_header_filter(mut self, filter: RedirectHeaderFilter) -> Self {
+    self.init.redirect_header_filter = Some(filter);
+    self
+  }
+
+  pub fn browser_compatible(mut self, browser_compatible: bool) -> Self {
+    self.init.browser_compatible = Some(browser_compatible);
+    self
+  }
+
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.init.timeout = Some(timeout);
+    self
+  }
+
+  pub fn duplex(mut self, duplex: RequestDuplex) -> Self {
+    self.init.duplex = Some(duplex);
+    self
+  }
+
+  pub fn allow_file_urls(mut self, allow_file_urls: bool) -> Self {
+    self.init.allow_file_urls = Some(allow_file_urls);
+    self
+  }
+
+  #[cfg(feature = "tls")]
+  pub fn tls(mut self, tls: crate::TlsOptions) -> Self {
+    self.init.tls = Some(tls);
+    self
+  }
+
+  #[cfg(feature = "tokio-fetch")]
+  pub fn pool(mut self, pool: crate::ConnectionPool) -> Self {
+    self.init.pool = Some(pool);
+    self
+  }
+
+  /// 校验并生成`RequestInit`
+  pub fn build(self) -> Result<RequestInit, RequestInitError> {
+    if let Some(error) = self.error {
+      return Err(error);
+    }
+    let init = self.init;
+    if let Some(ref headers) = init.headers {
+      validate_headers(headers)?;
+      validate_framing(headers, init.body.as_ref())?;
+    }
+    validate_options(
+      init.method.as_ref().unwrap_or(&Method::GET),
+      init.body.as_ref(),
+      init.duplex,
+      init.mode.unwrap_or_default(),
+      init.cache.unwrap_or_default(),
+      init.browser_compatible.unwrap_or_default(),
+    )?;
+    if init.timeout == Some(Duration::ZERO) {
+      return Err(RequestInitError::Conflict("timeout must be greater than zero".to_string()));
+    }
+    Ok(init)
+  }
+
+  fn default_header(self, name: &str, value: &str) -> Self {
+    if self.init.headers.as_ref().is_some_and(|headers| has_header(headers, name)) {
+      self
+    } else {
+      self.header(name, value)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn header_replaces_case_insensitively() {
+    let init = RequestInit::builder()
+      .header("content-type", "text/plain")
+      .header("Content-Type", "text/html")
+      .build()
+      .unwrap();
+    let headers = init.headers.unwrap();
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers.get("Content-Type").unwrap(), "text/html");
+  }
+
+  #[test]
+  fn reject_at_build_time() {
+    let err = RequestInit::builder().header("X-A", "a\r\nInjected: 1").build().err().unwrap();
+    assert_eq!(err, RequestInitError::InvalidHeaderValue("X-A".to_string()));
+    let err = RequestInit::builder().cache(RequestCache::OnlyIfCached).build().err().unwrap();
+    assert!(matches!(err, RequestInitError::Conflict(_)));
+    let stream = crate::streams::ReadableStream::empty();
+    let builder = || RequestInit::builder().method(Method::POST).browser_compatible(true);
+    assert!(builder().body(BodyInit::from_stream(stream)).build().is_err());
+    let stream = crate::streams::ReadableStream::empty();
+    assert!(builder().body(BodyInit::from_stream(stream)).duplex(RequestDuplex::Half).build().is_ok());
+  }
+
+  #[test]
+  fn reject_conflicting_framing() {
+    let builder = || RequestInit::builder().method(Method::POST);
+    let stream = || BodyInit::from_stream(crate::streams::ReadableStream::empty());
+    assert!(builder().header("Content-Length", "5").body(stream()).build().is_err());
+    assert!(builder().header("Content-Length", "5").body(stream().with_length(5)).build().is_ok());
+    assert!(builder().header("Transfer-Encoding", "chunked").body("hello").build().is_err());
+  }
+
+  #[test]
+  fn form_body() {
+    let init = RequestInit::builder()
+      .method(Method::POST)
+      .form(&URLSearchParams::new("a=1"))
+      .build()
+      .unwrap();
+    assert!(matches!(init.body, Some(BodyInit::Text(ref body)) if body == "a=1"));
+    assert!(init.headers.unwrap().get("Content-Type").unwrap().starts_with("application/x-www-form-urlencoded"));
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn json_body() {
+    let init = RequestInit::builder()
+      .method(Method::POST)
+      .header("content-type", "application/vnd.api+json")
+      .json(&serde_json::json!({ "a": 1 }))
+      .build()
+      .unwrap();
+    assert!(matches!(init.body, Some(BodyInit::Text(ref body)) if body == "{\"a\":1}"));
+    assert_eq!(init.headers.unwrap().get("content-type").unwrap(), "application/vnd.api+json");
+  }
+}
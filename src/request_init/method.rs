@@ -0,0 +1,159 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use super::{is_token, InvalidOptionError};
+
+/// 请求方法，`Extension`用于WebDAV等扩展方法
+///
+/// # Example
+/// ```
+/// use fetch_js::request_init::Method;
+/// assert_eq!("post".parse::<Method>().unwrap(), Method::POST);
+/// assert_eq!("PROPFIND".parse::<Method>().unwrap(), Method::Extension("PROPFIND".to_string()));
+/// assert_eq!("patch".parse::<Method>().unwrap().as_str(), "patch");
+/// assert!("BAD METHOD".parse::<Method>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Method {
+  GET,
+  POST,
+  PUT,
+  DELETE,
+  PATCH,
+  HEAD,
+  OPTIONS,
+  CONNECT,
+  TRACE,
+  Extension(String),
+}
+
+/// 浏览器中禁止使用的请求方法
+const FORBIDDEN_METHODS: [&str; 3] = ["CONNECT", "TRACE", "TRACK"];
+
+/// 按照规范只有这些方法会被规范化为大写
+const NORMALIZED_METHODS: [Method; 6] = [
+  Method::DELETE,
+  Method::GET,
+  Method::HEAD,
+  Method::OPTIONS,
+  Method::POST,
+  Method::PUT,
+];
+
+impl Method {
+  /// 创建扩展方法，方法名必须是合法的HTTP token
+  ///
+  /// 标准方法名按规范转换为对应的内置方法，`DELETE`、`GET`、`HEAD`、`OPTIONS`、`POST`和`PUT`不区分大小写
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::request_init::Method;
+  /// assert_eq!(Method::extension("get").unwrap(), Method::GET);
+  /// assert_eq!(Method::extension("PATCH").unwrap(), Method::PATCH);
+  /// assert_eq!(Method::extension("MKCOL").unwrap(), Method::Extension("MKCOL".to_string()));
+  /// ```
+  pub fn extension(method: &str) -> Result<Self, InvalidOptionError> {
+    if !is_token(method) {
+      return Err(InvalidOptionError {
+        kind: "method",
+        value: method.to_string(),
+      });
+    }
+    if let Some(normalized) = NORMALIZED_METHODS.iter().find(|m| m.as_str().eq_ignore_ascii_case(method)) {
+      return Ok(normalized.clone());
+    }
+    Ok(match method {
+      "PATCH" => Method::PATCH,
+      "CONNECT" => Method::CONNECT,
+      "TRACE" => Method::TRACE,
+      _ => Method::Extension(method.to_string()),
+    })
+  }
+
+  pub fn as_str(&self) -> &str {
+    match self {
+      Method::GET => "GET",
+      Method::POST => "POST",
+      Method::PUT => "PUT",
+      Method::DELETE => "DELETE",
+      Method::PATCH => "PATCH",
+      Method::HEAD => "HEAD",
+      Method::OPTIONS => "OPTIONS",
+      Method::CONNECT => "CONNECT",
+      Method::TRACE => "TRACE",
+      Method::Extension(method) => method,
+    }
+  }
+
+  /// 方法名是否为合法的HTTP token
+  pub fn is_valid(&self) -> bool {
+    is_token(self.as_str())
+  }
+
+  /// 是否为RFC 9110定义的幂等方法，这类请求重复发送与发送一次的效果相同
+  pub fn is_idempotent(&self) -> bool {
+    matches!(self, Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE)
+  }
+
+  /// 是否为浏览器禁止的`CONNECT`、`TRACE`或`TRACK`
+  pub fn is_forbidden(&self) -> bool {
+    FORBIDDEN_METHODS.iter().any(|m| m.eq_ignore_ascii_case(self.as_str()))
+  }
+}
+
+impl Display for Method {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.as_str())
+  }
+}
+
+impl FromStr for Method {
+  type Err = InvalidOptionError;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Method::extension(s)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn normalize() {
+    assert_eq!(Method::from_str("gEt"), Ok(Method::GET));
+    assert_eq!(Method::from_str("Delete"), Ok(Method::DELETE));
+    assert_eq!(Method::from_str("PATCH"), Ok(Method::PATCH));
+    assert_eq!(Method::from_str("purge"), Ok(Method::Extension("purge".to_string())));
+    assert_eq!(Method::from_str("connect"), Ok(Method::Extension("connect".to_string())));
+  }
+
+  #[test]
+  fn forbidden() {
+    assert!(Method::CONNECT.is_forbidden());
+    assert!(Method::from_str("track").unwrap().is_forbidden());
+    assert!(!Method::Extension("MKCOL".to_string()).is_forbidden());
+    assert!(!Method::Extension("A B".to_string()).is_valid());
+    assert!(Method::extension("").is_err());
+  }
+
+  #[test]
+  fn idempotent() {
+    assert!(Method::PUT.is_idempotent());
+    assert!(Method::extension("delete").unwrap().is_idempotent());
+    assert!(!Method::POST.is_idempotent());
+    assert!(!Method::PATCH.is_idempotent());
+  }
+
+  #[test]
+  fn extension_normalizes_standard_methods() {
+    assert_eq!(Method::extension("GET"), Ok(Method::GET));
+    assert_eq!(Method::extension("head"), Ok(Method::HEAD));
+    assert_eq!(Method::extension("TRACE"), Ok(Method::TRACE));
+    assert_eq!(Method::extension("trace"), Ok(Method::Extension("trace".to_string())));
+    // 规范化后的方法同样受GET/HEAD不能带正文的限制
+    let init = crate::request_init::RequestInit::builder()
+      .method(Method::extension("get").unwrap())
+      .body("x")
+      .build();
+    assert!(init.is_err());
+  }
+}
@@ -0,0 +1,198 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use thiserror::Error;
+use crate::url::URL;
+
+/// 解析`RequestInit`选项字符串失败时返回的错误
+#[derive(Debug, Error, PartialEq)]
+#[error("'{value}' is not a valid {kind} value")]
+pub struct InvalidOptionError {
+  pub kind: &'static str,
+  pub value: String,
+}
+
+macro_rules! string_enum {
+  (
+    $(#[$meta:meta])*
+    $name:ident, $kind:literal, default = $default:ident {
+      $($variant:ident => $value:literal,)*
+    }
+  ) => {
+    $(#[$meta])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum $name {
+      $($variant,)*
+    }
+
+    impl $name {
+      /// 返回规范中定义的字符串值
+      pub fn as_str(&self) -> &'static str {
+        match self {
+          $($name::$variant => $value,)*
+        }
+      }
+    }
+
+    impl Default for $name {
+      fn default() -> Self {
+        $name::$default
+      }
+    }
+
+    impl Display for $name {
+      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+      }
+    }
+
+    impl FromStr for $name {
+      type Err = InvalidOptionError;
+      fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+          $($value => Ok($name::$variant),)*
+          _ => Err(InvalidOptionError {
+            kind: $kind,
+            value: s.to_string(),
+          }),
+        }
+      }
+    }
+  };
+}
+
+string_enum! {
+  /// 请求模式，对应`RequestInit.mode`
+  RequestMode, "mode", default = Cors {
+    Cors => "cors",
+    NoCors => "no-cors",
+    SameOrigin => "same-origin",
+    Navigate => "navigate",
+  }
+}
+
+string_enum! {
+  /// 凭据模式，对应`RequestInit.credentials`
+  RequestCredentials, "credentials", default = SameOrigin {
+    Omit => "omit",
+    SameOrigin => "same-origin",
+    Include => "include",
+  }
+}
+
+string_enum! {
+  /// 缓存模式，对应`RequestInit.cache`
+  RequestCache, "cache", default = Default {
+    Default => "default",
+    NoStore => "no-store",
+    Reload => "reload",
+    NoCache => "no-cache",
+    ForceCache => "force-cache",
+    OnlyIfCached => "only-if-cached",
+  }
+}
+
+string_enum! {
+  /// 重定向模式，对应`RequestInit.redirect`
+  RequestRedirect, "redirect", default = Follow {
+    Follow => "follow",
+    Error => "error",
+    Manual => "manual",
+  }
+}
+
+string_enum! {
+  /// 双工模式，对应`RequestInit.duplex`，目前只支持发送完正文后再读取响应的`half`
+  RequestDuplex, "duplex", default = Half {
+    Half => "half",
+  }
+}
+
+string_enum! {
+  /// Referrer策略，对应`RequestInit.referrerPolicy`
+  ReferrerPolicy, "referrer policy", default = StrictOriginWhenCrossOrigin {
+    NoReferrer => "no-referrer",
+    NoReferrerWhenDowngrade => "no-referrer-when-downgrade",
+    SameOrigin => "same-origin",
+    Origin => "origin",
+    StrictOrigin => "strict-origin",
+    OriginWhenCrossOrigin => "origin-when-cross-origin",
+    StrictOriginWhenCrossOrigin => "strict-origin-when-cross-origin",
+    UnsafeUrl => "unsafe-url",
+  }
+}
+
+impl ReferrerPolicy {
+  /// 按照策略计算发往`target`的请求应携带的`Referer`
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::url::URL;
+  /// use fetch_js::request_init::ReferrerPolicy;
+  ///
+  /// let referrer = URL::new("https://example.com/page?secret=1");
+  /// let same = URL::new("https://example.com/api");
+  /// let cross = URL::new("https://cdn.example.net/lib.js");
+  /// let policy = ReferrerPolicy::StrictOriginWhenCrossOrigin;
+  /// assert_eq!(policy.referrer(&referrer, &same), Some("https://example.com/page?secret=1".to_string()));
+  /// assert_eq!(policy.referrer(&referrer, &cross), Some("https://example.com/".to_string()));
+  /// ```
+  pub fn referrer(&self, referrer: &URL, target: &URL) -> Option<String> {
+    let protocol = referrer.get_protocol();
+    if protocol != "http:" && protocol != "https:" {
+      return None;
+    }
+    let full = format!("{}{}{}", referrer.get_origin(), referrer.get_pathname(), referrer.get_search());
+    let origin = format!("{}/", referrer.get_origin());
+    let same_origin = referrer.get_origin() == target.get_origin();
+    let downgrade = protocol == "https:" && target.get_protocol() != "https:";
+    match self {
+      ReferrerPolicy::NoReferrer => None,
+      ReferrerPolicy::NoReferrerWhenDowngrade => (!downgrade).then_some(full),
+      ReferrerPolicy::SameOrigin => same_origin.then_some(full),
+      ReferrerPolicy::Origin => Some(origin),
+      ReferrerPolicy::StrictOrigin => (!downgrade).then_some(origin),
+      ReferrerPolicy::OriginWhenCrossOrigin => Some(if same_origin { full } else { origin }),
+      ReferrerPolicy::StrictOriginWhenCrossOrigin => {
+        if same_origin {
+          Some(full)
+        } else if downgrade {
+          None
+        } else {
+          Some(origin)
+        }
+      },
+      ReferrerPolicy::UnsafeUrl => Some(full),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    for value in ["follow", "error", "manual"] {
+      assert_eq!(value.parse::<RequestRedirect>().unwrap().to_string(), value);
+    }
+    assert_eq!(RequestCache::from_str("only-if-cached"), Ok(RequestCache::OnlyIfCached));
+    assert_eq!(ReferrerPolicy::from_str("unsafe-url"), Ok(ReferrerPolicy::UnsafeUrl));
+  }
+
+  #[test]
+  fn reject_typo() {
+    let err = "folow".parse::<RequestRedirect>().unwrap_err();
+    assert_eq!(err, InvalidOptionError { kind: "redirect", value: "folow".to_string() });
+    assert!("CORS".parse::<RequestMode>().is_err());
+  }
+
+  #[test]
+  fn referrer_downgrade() {
+    let referrer = URL::new("https://example.com/page#top");
+    let target = URL::new("http://example.com/page");
+    assert_eq!(ReferrerPolicy::StrictOriginWhenCrossOrigin.referrer(&referrer, &target), None);
+    assert_eq!(ReferrerPolicy::NoReferrerWhenDowngrade.referrer(&referrer, &target), None);
+    assert_eq!(ReferrerPolicy::UnsafeUrl.referrer(&referrer, &target), Some("https://example.com/page".to_string()));
+    assert_eq!(ReferrerPolicy::Origin.referrer(&referrer, &target), Some("https://example.com/".to_string()));
+  }
+}
@@ -0,0 +1,33 @@
+mod abortable;
+mod readable;
+mod tee;
+mod transform;
+mod writable;
+
+pub use readable::*;
+pub use transform::*;
+pub use writable::*;
+
+use thiserror::Error;
+
+/// 流被`AbortSignal`中止时，读取或写入返回的`io::Error`内部的错误
+///
+/// 可以通过`io::Error::get_ref`向下转型获取中止原因
+#[derive(Debug, Clone, Error, PartialEq)]
+#[error("stream aborted: {}", .reason.as_deref().unwrap_or("no reason"))]
+pub struct AbortError {
+  pub reason: Option<String>,
+}
+
+impl AbortError {
+  /// 从`io::Error`中取出中止错误，不是由中止引起时返回`None`
+  pub fn from_io(e: &std::io::Error) -> Option<&AbortError> {
+    e.get_ref().and_then(|inner| inner.downcast_ref::<AbortError>())
+  }
+}
+
+impl From<AbortError> for std::io::Error {
+  fn from(e: AbortError) -> Self {
+    std::io::Error::new(std::io::ErrorKind::Interrupted, e)
+  }
+}
@@ -0,0 +1,91 @@
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use futures_core::Stream;
+use futures_util::StreamExt;
+use crate::abort_controller::AbortSignal;
+use crate::event_target::AddEventListenerOptions;
+use super::{AbortError, ReadableStream};
+
+/// 在信号中止时产出`AbortError`并丢弃底层流
+pub(super) struct Abortable<T> {
+  inner: Option<ReadableStream<T>>,
+  signal: AbortSignal,
+  waker: Arc<Mutex<Option<Waker>>>,
+  listener: Option<usize>,
+}
+
+impl<T> Abortable<T> {
+  pub(super) fn new(inner: ReadableStream<T>, signal: &AbortSignal) -> Self {
+    let waker = Arc::new(Mutex::new(None::<Waker>));
+    let slot = waker.clone();
+    let listener = signal.add_event_listener("abort", move |_| {
+      if let Some(waker) = slot.lock().unwrap().take() {
+        waker.wake();
+      }
+    }, AddEventListenerOptions {
+      once: true,
+      signal: None,
+    });
+    Self {
+      inner: Some(inner),
+      signal: signal.clone(),
+      waker,
+      listener,
+    }
+  }
+
+  fn abort(&mut self) -> Poll<Option<io::Result<T>>> {
+    self.inner = None;
+    Poll::Ready(Some(Err(AbortError { reason: self.signal.reason() }.into())))
+  }
+}
+
+impl<T> Stream for Abortable<T> {
+  type Item = io::Result<T>;
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+    if this.inner.is_none() {
+      return Poll::Ready(None);
+    }
+    if this.signal.aborted() {
+      return this.abort();
+    }
+    *this.waker.lock().unwrap() = Some(cx.waker().clone());
+    // 注册waker之前信号可能已经中止
+    if this.signal.aborted() {
+      return this.abort();
+    }
+    this.inner.as_mut().unwrap().poll_next_unpin(cx)
+  }
+}
+
+impl<T> Drop for Abortable<T> {
+  fn drop(&mut self) {
+    if let Some(listener) = self.listener {
+      self.signal.remove_event_listener("abort", listener);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use futures_util::FutureExt;
+  use crate::abort_controller::AbortController;
+
+  #[test]
+  fn abort_pending_read() {
+    let controller = AbortController::new();
+    let (_source, stream) = ReadableStream::<u8>::channel(1);
+    let mut stream = stream.abortable(&controller.signal);
+    assert!(stream.next().now_or_never().is_none());
+    controller.abort(Some("stop".to_string()));
+    let e = stream.next().now_or_never().unwrap().unwrap().unwrap_err();
+    assert_eq!(AbortError::from_io(&e).unwrap().reason.as_deref(), Some("stop"));
+    assert!(stream.next().now_or_never().unwrap().is_none());
+    drop(stream);
+    assert_eq!(controller.signal.listener_count("abort"), 0);
+  }
+}
@@ -0,0 +1,320 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures_channel::mpsc;
+use futures_core::Stream;
+use futures_util::{SinkExt, StreamExt};
+use crate::abort_controller::AbortSignal;
+use super::abortable::Abortable;
+use super::tee::{self, TeeLimit};
+use super::{TransformStream, WritableStream};
+
+type BoxStream<T> = Pin<Box<dyn Stream<Item = io::Result<T>> + Send>>;
+
+/// 对应JS中的`ReadableStream`，按顺序产出数据块
+///
+/// 实现了`futures::Stream`，字节流还可以通过`into_async_read`转换为`tokio::io::AsyncRead`
+pub struct ReadableStream<T> {
+  inner: BoxStream<T>,
+}
+
+impl<T> ReadableStream<T> {
+  /// 由任意`Stream`创建
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::streams::ReadableStream;
+  /// let stream = ReadableStream::from_stream(futures_util::stream::iter(vec![Ok(1), Ok(2)]));
+  /// ```
+  pub fn from_stream<S>(stream: S) -> Self
+  where
+    S: Stream<Item = io::Result<T>> + Send + 'static,
+  {
+    Self {
+      inner: Box::pin(stream),
+    }
+  }
+}
+
+impl<T: Send + 'static> ReadableStream<T> {
+  /// 创建一个没有数据的流
+  pub fn empty() -> Self {
+    Self::from_stream(futures_util::stream::empty())
+  }
+
+  /// 创建由控制器推送数据的流
+  ///
+  /// 队列中的数据块达到`high_water_mark`时`enqueue`会等待读取方消费，以此形成背压；
+  /// 控制器被丢弃后流在读完队列后结束
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::streams::ReadableStream;
+  ///
+  /// # futures_util::FutureExt::now_or_never(async {
+  /// let (mut controller, stream) = ReadableStream::channel(2);
+  /// controller.enqueue("a").await.unwrap();
+  /// controller.enqueue("b").await.unwrap();
+  /// controller.close();
+  ///
+  /// let mut reader = stream.get_reader();
+  /// assert_eq!(reader.read().await.unwrap(), Some("a"));
+  /// assert_eq!(reader.read().await.unwrap(), Some("b"));
+  /// assert_eq!(reader.read().await.unwrap(), None);
+  /// # }).unwrap();
+  /// ```
+  pub fn channel(high_water_mark: usize) -> (ReadableStreamController<T>, Self) {
+    // 每个发送端额外占用一个位置
+    let (sender, receiver) = mpsc::channel(high_water_mark.saturating_sub(1));
+    (ReadableStreamController { sender }, Self::from_stream(receiver))
+  }
+
+  /// 获取读取器，流在读取器释放前被其独占
+  pub fn get_reader(self) -> ReadableStreamDefaultReader<T> {
+    ReadableStreamDefaultReader { stream: Some(self) }
+  }
+
+  /// 分成两个可以独立读取的流，每个数据块都会被克隆
+  ///
+  /// 较快的一方读到的数据会为另一方缓存，缓存不设上限；需要限制内存时使用`tee_with_capacity`
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::streams::ReadableStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # futures_util::FutureExt::now_or_never(async {
+  /// let stream = ReadableStream::from_stream(futures_util::stream::iter(vec![Ok(1), Ok(2)]));
+  /// let (left, right) = stream.tee();
+  /// let left: Vec<_> = left.map(Result::unwrap).collect().await;
+  /// let right: Vec<_> = right.map(Result::unwrap).collect().await;
+  /// assert_eq!(left, right);
+  /// # }).unwrap();
+  /// ```
+  pub fn tee(self) -> (Self, Self)
+  where
+    T: Clone,
+  {
+    tee::tee(self, None)
+  }
+
+  /// 与`tee`相同，但为另一方缓存的数据块达到`capacity`时较快的一方会等待
+  ///
+  /// 两个流需要被并发读取，否则先读取的一方会在缓存满后一直等待
+  pub fn tee_with_capacity(self, capacity: usize) -> (Self, Self)
+  where
+    T: Clone,
+  {
+    tee::tee(self, Some(TeeLimit {
+      capacity: capacity.max(1),
+      weight: |_| 1,
+      error_on_overflow: false,
+    }))
+  }
+
+  /// 在信号中止时以`AbortError`结束流，并释放底层数据源
+  pub fn abortable(self, signal: &AbortSignal) -> Self {
+    Self::from_stream(Abortable::new(self, signal))
+  }
+
+  /// 通过`TransformStream`转换每个数据块
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::streams::{ReadableStream, TransformStream};
+  /// use futures_util::StreamExt;
+  ///
+  /// # futures_util::FutureExt::now_or_never(async {
+  /// let stream = ReadableStream::from_stream(futures_util::stream::iter(vec![Ok("a,b"), Ok("c")]));
+  /// let split = TransformStream::new(|chunk: &str| Ok(chunk.split(',').map(String::from).collect()));
+  /// let chunks: Vec<_> = stream.pipe_through(split).map(Result::unwrap).collect().await;
+  /// assert_eq!(chunks, ["a", "b", "c"]);
+  /// # }).unwrap();
+  /// ```
+  pub fn pipe_through<U: Send + 'static>(self, transform: TransformStream<T, U>) -> ReadableStream<U> {
+    transform.apply(self)
+  }
+
+  /// 将所有数据块写入`dest`，完成后关闭`dest`
+  ///
+  /// 读取出错时会中止`dest`，写入出错时会取消当前流；`options.signal`中止时两者同时发生
+  pub async fn pipe_to(self, dest: &mut WritableStream<T>, options: PipeOptions) -> io::Result<()> {
+    let mut source = match options.signal {
+      Some(ref signal) => self.abortable(signal),
+      None => self,
+    };
+    while let Some(chunk) = source.next().await {
+      match chunk {
+        Ok(chunk) => dest.write(chunk).await?,
+        Err(e) => {
+          if !options.prevent_abort {
+            dest.abort(super::AbortError::from_io(&e).and_then(|e| e.reason.clone()));
+          }
+          return Err(e);
+        },
+      }
+    }
+    if !options.prevent_close {
+      dest.close().await?;
+    }
+    Ok(())
+  }
+}
+
+impl ReadableStream<bytes::Bytes> {
+  /// 与`tee`相同，但为另一方缓存的数据最多为`max_buffered`字节
+  ///
+  /// 超出时较快的一方返回`OutOfMemory`错误并退出，另一方仍能读到完整的数据，
+  /// 因此两个流可以先后读取而不会互相等待
+  pub fn tee_with_limit(self, max_buffered: usize) -> (Self, Self) {
+    tee::tee(self, Some(TeeLimit {
+      capacity: max_buffered,
+      weight: bytes::Bytes::len,
+      error_on_overflow: true,
+    }))
+  }
+}
+
+#[cfg(feature = "tokio-fetch")]
+impl ReadableStream<bytes::Bytes> {
+  /// 转换为`tokio::io::AsyncRead`，可以直接用`tokio::io::copy`写入文件或者其他连接
+  pub fn into_async_read(self) -> impl tokio::io::AsyncRead + Send + Unpin {
+    tokio_util::io::StreamReader::new(self)
+  }
+
+  /// 将所有数据写入`writer`并返回写入的字节数，不会关闭`writer`
+  pub async fn pipe_to_writer<W>(mut self, writer: &mut W) -> io::Result<u64>
+  where
+    W: tokio::io::AsyncWrite + Unpin + ?Sized,
+  {
+    use tokio::io::AsyncWriteExt;
+    let mut written = 0;
+    while let Some(chunk) = self.next().await {
+      let chunk = chunk?;
+      writer.write_all(&chunk).await?;
+      written += chunk.len() as u64;
+    }
+    writer.flush().await?;
+    Ok(written)
+  }
+}
+
+impl<T> Stream for ReadableStream<T> {
+  type Item = io::Result<T>;
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self.inner.as_mut().poll_next(cx)
+  }
+}
+
+impl<T> std::fmt::Debug for ReadableStream<T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ReadableStream").finish_non_exhaustive()
+  }
+}
+
+/// `ReadableStream::pipe_to`的选项
+#[derive(Clone, Default)]
+pub struct PipeOptions {
+  /// 读取结束后不关闭目标流
+  pub prevent_close: bool,
+  /// 读取出错后不中止目标流
+  pub prevent_abort: bool,
+  pub signal: Option<AbortSignal>,
+}
+
+/// 对应JS中的`ReadableStreamDefaultReader`
+pub struct ReadableStreamDefaultReader<T> {
+  stream: Option<ReadableStream<T>>,
+}
+
+impl<T: Send + 'static> ReadableStreamDefaultReader<T> {
+  /// 读取下一个数据块，流结束后返回`None`
+  pub async fn read(&mut self) -> io::Result<Option<T>> {
+    match self.stream {
+      Some(ref mut stream) => stream.next().await.transpose(),
+      None => Ok(None),
+    }
+  }
+
+  /// 取消读取并释放底层数据源，之后的读取都返回`None`
+  pub fn cancel(&mut self) {
+    self.stream = None;
+  }
+
+  /// 释放读取器并取回剩余的流，已取消时返回空流
+  pub fn release_lock(self) -> ReadableStream<T> {
+    self.stream.unwrap_or_else(ReadableStream::empty)
+  }
+}
+
+/// 向`ReadableStream::channel`创建的流推送数据
+pub struct ReadableStreamController<T> {
+  sender: mpsc::Sender<io::Result<T>>,
+}
+
+impl<T> ReadableStreamController<T> {
+  /// 推送数据块，队列已满时等待；读取方已取消时返回`BrokenPipe`错误
+  pub async fn enqueue(&mut self, chunk: T) -> io::Result<()> {
+    self.sender.feed(Ok(chunk)).await
+      .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "stream has been cancelled"))
+  }
+
+  /// 以错误结束流
+  pub async fn error(mut self, e: io::Error) {
+    let _ = self.sender.feed(Err(e)).await;
+  }
+
+  /// 关闭流，读取方读完队列中的数据后结束
+  pub fn close(self) {}
+
+  /// 读取方是否已经取消
+  pub fn is_cancelled(&self) -> bool {
+    self.sender.is_closed()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use futures_util::FutureExt;
+
+  #[test]
+  fn backpressure_and_cancel() {
+    let (mut controller, stream) = ReadableStream::channel(2);
+    assert!(controller.enqueue(1).now_or_never().unwrap().is_ok());
+    assert!(controller.enqueue(2).now_or_never().unwrap().is_ok());
+    // 达到高水位线后需要等待读取
+    assert!(controller.enqueue(3).now_or_never().is_none());
+
+    let mut reader = stream.get_reader();
+    assert_eq!(reader.read().now_or_never().unwrap().unwrap(), Some(1));
+    reader.cancel();
+    assert_eq!(reader.read().now_or_never().unwrap().unwrap(), None);
+    assert!(controller.is_cancelled());
+    assert!(controller.enqueue(4).now_or_never().unwrap().is_err());
+  }
+
+  #[cfg(feature = "tokio-fetch")]
+  #[tokio::test]
+  async fn stream_and_async_read() {
+    use bytes::Bytes;
+    use tokio::io::AsyncReadExt;
+
+    let chunks = || vec![Ok(Bytes::from("hello ")), Ok(Bytes::from("world"))];
+    let mut stream = ReadableStream::from_stream(futures_util::stream::iter(chunks()));
+    assert_eq!(stream.next().await.unwrap().unwrap(), "hello ");
+    assert_eq!(stream.next().await.unwrap().unwrap(), "world");
+    assert!(stream.next().await.is_none());
+
+    let mut reader = ReadableStream::from_stream(futures_util::stream::iter(chunks())).into_async_read();
+    let mut text = String::new();
+    reader.read_to_string(&mut text).await.unwrap();
+    assert_eq!(text, "hello world");
+
+    let mut output = Vec::new();
+    let written = ReadableStream::from_stream(futures_util::stream::iter(chunks()))
+      .pipe_to_writer(&mut output).await.unwrap();
+    assert_eq!(written, 11);
+    assert_eq!(output, b"hello world");
+  }
+}
@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use futures_core::Stream;
+use futures_util::StreamExt;
+use super::ReadableStream;
+
+/// 底层流只保存最后一次轮询的waker，用它同时唤醒两个分支
+#[derive(Default)]
+struct TeeWaker {
+  wakers: Mutex<[Option<Waker>; 2]>,
+}
+
+impl TeeWaker {
+  fn register(&self, index: usize, waker: &Waker) {
+    self.wakers.lock().unwrap()[index] = Some(waker.clone());
+  }
+
+  fn wake_branch(&self, index: usize) {
+    let waker = self.wakers.lock().unwrap()[index].take();
+    if let Some(waker) = waker {
+      waker.wake();
+    }
+  }
+}
+
+impl Wake for TeeWaker {
+  fn wake(self: Arc<Self>) {
+    self.wake_by_ref();
+  }
+
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.wake_branch(0);
+    self.wake_branch(1);
+  }
+}
+
+/// 为另一方缓存的数据上限，`weight`计算每个数据块占用的大小
+pub(super) struct TeeLimit<T> {
+  pub(super) capacity: usize,
+  pub(super) weight: fn(&T) -> usize,
+  /// 超出上限时较快的一方返回错误并退出，否则等待另一方读取
+  pub(super) error_on_overflow: bool,
+}
+
+struct TeeState<T> {
+  source: ReadableStream<T>,
+  done: bool,
+  queues: [VecDeque<io::Result<T>>; 2],
+  buffered: [usize; 2],
+  alive: [bool; 2],
+  limit: Option<TeeLimit<T>>,
+}
+
+impl<T> TeeState<T> {
+  fn weight(&self, item: &io::Result<T>) -> usize {
+    match (&self.limit, item) {
+      (Some(limit), Ok(chunk)) => (limit.weight)(chunk),
+      _ => 0,
+    }
+  }
+}
+
+struct TeeBranch<T> {
+  state: Arc<Mutex<TeeState<T>>>,
+  waker: Arc<TeeWaker>,
+  index: usize,
+}
+
+pub(super) fn tee<T>(source: ReadableStream<T>, limit: Option<TeeLimit<T>>) -> (ReadableStream<T>, ReadableStream<T>)
+where
+  T: Clone + Send + 'static,
+{
+  let state = Arc::new(Mutex::new(TeeState {
+    source,
+    done: false,
+    queues: [VecDeque::new(), VecDeque::new()],
+    buffered: [0, 0],
+    alive: [true, true],
+    limit,
+  }));
+  let waker = Arc::new(TeeWaker::default());
+  let branch = |index| ReadableStream::from_stream(TeeBranch {
+    state: state.clone(),
+    waker: waker.clone(),
+    index,
+  });
+  (branch(0), branch(1))
+}
+
+impl<T: Clone> Stream for TeeBranch<T> {
+  type Item = io::Result<T>;
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+    let (index, other) = (this.index, 1 - this.index);
+    let mut state = this.state.lock().unwrap();
+    if let Some(item) = state.queues[index].pop_front() {
+      state.buffered[index] -= state.weight(&item);
+      // 另一方可能在等待缓存腾出空间
+      this.waker.wake_branch(other);
+      return Poll::Ready(Some(item));
+    }
+    if state.done || !state.alive[index] {
+      return Poll::Ready(None);
+    }
+    this.waker.register(index, cx.waker());
+    if let Some(ref limit) = state.limit {
+      if !limit.error_on_overflow && state.alive[other] && state.buffered[other] >= limit.capacity {
+        return Poll::Pending;
+      }
+    }
+    let waker = Waker::from(this.waker.clone());
+    match state.source.poll_next_unpin(&mut Context::from_waker(&waker)) {
+      Poll::Ready(Some(item)) => {
+        if !state.alive[other] {
+          return Poll::Ready(Some(item));
+        }
+        let weight = state.weight(&item);
+        if let Some(ref limit) = state.limit {
+          if limit.error_on_overflow && state.buffered[other] + weight > limit.capacity {
+            // 数据块完整地交给另一方，当前一方退出
+            let capacity = limit.capacity;
+            state.alive[index] = false;
+            state.queues[other].push_back(item);
+            state.buffered[other] += weight;
+            this.waker.wake_branch(other);
+            let message = format!("tee buffer limit of {} exceeded", capacity);
+            return Poll::Ready(Some(Err(io::Error::new(io::ErrorKind::OutOfMemory, message))));
+          }
+        }
+        let copy = match item {
+          Ok(ref chunk) => Ok(chunk.clone()),
+          Err(ref e) => Err(io::Error::new(e.kind(), e.to_string())),
+        };
+        state.queues[other].push_back(copy);
+        state.buffered[other] += weight;
+        this.waker.wake_branch(other);
+        Poll::Ready(Some(item))
+      },
+      Poll::Ready(None) => {
+        state.done = true;
+        this.waker.wake_branch(other);
+        Poll::Ready(None)
+      },
+      Poll::Pending => Poll::Pending,
+    }
+  }
+}
+
+impl<T> Drop for TeeBranch<T> {
+  fn drop(&mut self) {
+    let mut state = self.state.lock().unwrap();
+    state.alive[self.index] = false;
+    state.queues[self.index].clear();
+    state.buffered[self.index] = 0;
+    drop(state);
+    self.waker.wake_branch(1 - self.index);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+  use futures_util::FutureExt;
+  use super::*;
+
+  #[test]
+  fn bounded_tee() {
+    let (mut controller, stream) = ReadableStream::channel(8);
+    for i in 0..4 {
+      controller.enqueue(i).now_or_never().unwrap().unwrap();
+    }
+    controller.close();
+    let (mut left, mut right) = stream.tee_with_capacity(2);
+    assert_eq!(left.next().now_or_never().unwrap().unwrap().unwrap(), 0);
+    assert_eq!(left.next().now_or_never().unwrap().unwrap().unwrap(), 1);
+    // right缓存了两个数据块，left需要等待
+    assert!(left.next().now_or_never().is_none());
+    assert_eq!(right.next().now_or_never().unwrap().unwrap().unwrap(), 0);
+    assert_eq!(left.next().now_or_never().unwrap().unwrap().unwrap(), 2);
+
+    // 一方被丢弃后另一方不再受限
+    drop(right);
+    assert_eq!(left.next().now_or_never().unwrap().unwrap().unwrap(), 3);
+    assert!(left.next().now_or_never().unwrap().is_none());
+  }
+
+  #[test]
+  fn byte_limited_tee() {
+    let chunks = vec![Ok(Bytes::from("abc")), Ok(Bytes::from("defg"))];
+    let (mut left, mut right) = ReadableStream::from_stream(futures_util::stream::iter(chunks)).tee_with_limit(5);
+    assert_eq!(left.next().now_or_never().unwrap().unwrap().unwrap(), "abc");
+    // 缓存会达到7字节，left出错退出，right仍能读到全部数据
+    let e = left.next().now_or_never().unwrap().unwrap().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::OutOfMemory);
+    assert!(left.next().now_or_never().unwrap().is_none());
+    assert_eq!(right.next().now_or_never().unwrap().unwrap().unwrap(), "abc");
+    assert_eq!(right.next().now_or_never().unwrap().unwrap().unwrap(), "defg");
+    assert!(right.next().now_or_never().unwrap().is_none());
+  }
+}
@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::io;
+use futures_util::StreamExt;
+use super::ReadableStream;
+
+type TransformFn<I, O> = Box<dyn FnMut(I) -> io::Result<Vec<O>> + Send>;
+type FlushFn<O> = Box<dyn FnOnce() -> io::Result<Vec<O>> + Send>;
+
+/// 对应JS中的`TransformStream`，通过`ReadableStream::pipe_through`逐块转换数据
+///
+/// 每个输入块可以产出零个或多个输出块，输入结束后由`flush`产出剩余的数据；
+/// 转换按需进行，读取方的背压会传递到上游
+pub struct TransformStream<I, O> {
+  transform: TransformFn<I, O>,
+  flush: Option<FlushFn<O>>,
+}
+
+impl<I, O> TransformStream<I, O> {
+  pub fn new<F>(transform: F) -> Self
+  where
+    F: FnMut(I) -> io::Result<Vec<O>> + Send + 'static,
+  {
+    Self {
+      transform: Box::new(transform),
+      flush: None,
+    }
+  }
+
+  /// 设置输入结束时调用的函数
+  pub fn with_flush<F>(mut self, flush: F) -> Self
+  where
+    F: FnOnce() -> io::Result<Vec<O>> + Send + 'static,
+  {
+    self.flush = Some(Box::new(flush));
+    self
+  }
+}
+
+impl<I: Send + 'static, O: Send + 'static> TransformStream<I, O> {
+  pub(super) fn apply(self, source: ReadableStream<I>) -> ReadableStream<O> {
+    struct State<I, O> {
+      source: Option<ReadableStream<I>>,
+      pending: VecDeque<O>,
+      transform: TransformStream<I, O>,
+    }
+
+    let state = State {
+      source: Some(source),
+      pending: VecDeque::new(),
+      transform: self,
+    };
+    ReadableStream::from_stream(futures_util::stream::unfold(state, |mut state| async move {
+      loop {
+        if let Some(chunk) = state.pending.pop_front() {
+          return Some((Ok(chunk), state));
+        }
+        let output = match state.source.as_mut()?.next().await {
+          Some(Ok(chunk)) => (state.transform.transform)(chunk),
+          Some(Err(e)) => Err(e),
+          None => {
+            state.source = None;
+            match state.transform.flush.take() {
+              Some(flush) => flush(),
+              None => return None,
+            }
+          },
+        };
+        match output {
+          Ok(chunks) => state.pending.extend(chunks),
+          Err(e) => {
+            state.source = None;
+            return Some((Err(e), state));
+          },
+        }
+      }
+    }))
+  }
+}
+
+impl<I, O> std::fmt::Debug for TransformStream<I, O> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("TransformStream").finish_non_exhaustive()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use futures_util::FutureExt;
+  use super::*;
+
+  #[test]
+  fn transform_with_flush() {
+    // 按行切分，末尾不完整的行在flush时输出
+    let buffer = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
+    let rest = buffer.clone();
+    let lines = TransformStream::new(move |chunk: &str| {
+      let mut buffer = buffer.lock().unwrap();
+      buffer.push_str(chunk);
+      let mut lines: Vec<String> = buffer.split('\n').map(String::from).collect();
+      *buffer = lines.pop().unwrap_or_default();
+      Ok(lines)
+    }).with_flush(move || Ok(vec![rest.lock().unwrap().clone()]));
+
+    let stream = ReadableStream::from_stream(futures_util::stream::iter(vec![Ok("a\nb"), Ok("c\nd")]));
+    let lines: Vec<_> = stream.pipe_through(lines).map(Result::unwrap).collect().now_or_never().unwrap();
+    assert_eq!(lines, ["a", "bc", "d"]);
+  }
+}
@@ -0,0 +1,197 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use futures_sink::Sink;
+use futures_util::SinkExt;
+use super::AbortError;
+
+type BoxSink<T> = Pin<Box<dyn Sink<T, Error = io::Error> + Send>>;
+
+/// 对应JS中的`WritableStream`，按顺序写入数据块
+///
+/// 每次`write`都会等待底层写入完成，以此向上游传递背压
+///
+/// # Example
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use fetch_js::streams::{PipeOptions, ReadableStream, WritableStream};
+///
+/// # futures_util::FutureExt::now_or_never(async {
+/// let received = Arc::new(Mutex::new(vec![]));
+/// let sink = received.clone();
+/// let mut dest = WritableStream::new(move |chunk: u8| {
+///   sink.lock().unwrap().push(chunk);
+///   async { Ok(()) }
+/// });
+/// let source = ReadableStream::from_stream(futures_util::stream::iter(vec![Ok(1), Ok(2)]));
+/// source.pipe_to(&mut dest, PipeOptions::default()).await.unwrap();
+/// assert_eq!(*received.lock().unwrap(), [1, 2]);
+/// # }).unwrap();
+/// ```
+pub struct WritableStream<T> {
+  sink: BoxSink<T>,
+  aborted: Option<AbortError>,
+}
+
+impl<T> WritableStream<T> {
+  /// 由任意`Sink`创建
+  pub fn from_sink<S>(sink: S) -> Self
+  where
+    S: Sink<T, Error = io::Error> + Send + 'static,
+  {
+    Self {
+      sink: Box::pin(sink),
+      aborted: None,
+    }
+  }
+
+  /// 由异步写入函数创建
+  pub fn new<F, Fut>(mut write: F) -> Self
+  where
+    F: FnMut(T) -> Fut + Send + 'static,
+    Fut: Future<Output = io::Result<()>> + Send + 'static,
+    T: 'static,
+  {
+    Self::from_sink(futures_util::sink::unfold((), move |(), chunk| write(chunk)))
+  }
+
+  /// 写入一个数据块，中止后返回`AbortError`
+  pub async fn write(&mut self, chunk: T) -> io::Result<()> {
+    if let Some(ref e) = self.aborted {
+      return Err(e.clone().into());
+    }
+    self.sink.send(chunk).await
+  }
+
+  /// 写入所有缓冲的数据并关闭底层目标
+  pub async fn close(&mut self) -> io::Result<()> {
+    if let Some(ref e) = self.aborted {
+      return Err(e.clone().into());
+    }
+    self.sink.close().await
+  }
+
+  /// 中止写入，之后的写入都会返回`AbortError`
+  pub fn abort(&mut self, reason: Option<String>) {
+    self.aborted.get_or_insert(AbortError { reason });
+  }
+}
+
+#[cfg(feature = "tokio-fetch")]
+impl WritableStream<bytes::Bytes> {
+  /// 由`tokio::io::AsyncWrite`创建，关闭时会调用`shutdown`
+  pub fn from_async_write<W>(writer: W) -> Self
+  where
+    W: tokio::io::AsyncWrite + Send + Unpin + 'static,
+  {
+    Self::from_sink(async_write::AsyncWriteSink {
+      writer,
+      pending: bytes::Bytes::new(),
+    })
+  }
+}
+
+impl<T> std::fmt::Debug for WritableStream<T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("WritableStream")
+      .field("aborted", &self.aborted.is_some())
+      .finish_non_exhaustive()
+  }
+}
+
+#[cfg(feature = "tokio-fetch")]
+mod async_write {
+  use std::io;
+  use std::pin::Pin;
+  use std::task::{ready, Context, Poll};
+  use bytes::{Buf, Bytes};
+  use futures_sink::Sink;
+  use tokio::io::AsyncWrite;
+
+  pub(super) struct AsyncWriteSink<W> {
+    pub(super) writer: W,
+    pub(super) pending: Bytes,
+  }
+
+  impl<W: AsyncWrite + Unpin> AsyncWriteSink<W> {
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+      while !self.pending.is_empty() {
+        let n = ready!(Pin::new(&mut self.writer).poll_write(cx, &self.pending))?;
+        if n == 0 {
+          return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+        }
+        self.pending.advance(n);
+      }
+      Poll::Ready(Ok(()))
+    }
+  }
+
+  impl<W: AsyncWrite + Unpin> Sink<Bytes> for AsyncWriteSink<W> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+      self.get_mut().poll_write_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> io::Result<()> {
+      self.get_mut().pending = item;
+      Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+      let this = self.get_mut();
+      ready!(this.poll_write_pending(cx))?;
+      Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+      ready!(self.as_mut().poll_flush(cx))?;
+      Pin::new(&mut self.get_mut().writer).poll_shutdown(cx)
+    }
+  }
+}
+
+#[cfg(feature = "tokio-fetch")]
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+  use crate::abort_controller::AbortController;
+  use crate::streams::{AbortError, PipeOptions, ReadableStream};
+  use super::*;
+
+  #[tokio::test]
+  async fn pipe_to_async_write() {
+    let (client, mut server) = tokio::io::duplex(4);
+    let source = ReadableStream::from_stream(futures_util::stream::iter(vec![
+      Ok(Bytes::from("hello ")),
+      Ok(Bytes::from("world")),
+    ]));
+    let mut dest = WritableStream::from_async_write(client);
+    let (result, received) = tokio::join!(source.pipe_to(&mut dest, PipeOptions::default()), async {
+      let mut received = String::new();
+      tokio::io::AsyncReadExt::read_to_string(&mut server, &mut received).await.unwrap();
+      received
+    });
+    result.unwrap();
+    assert_eq!(received, "hello world");
+  }
+
+  #[tokio::test]
+  async fn pipe_aborted_by_signal() {
+    let controller = AbortController::new();
+    let (_source, stream) = ReadableStream::<Bytes>::channel(1);
+    let mut dest = WritableStream::from_async_write(tokio::io::sink());
+    let options = PipeOptions {
+      signal: Some(controller.signal.clone()),
+      ..Default::default()
+    };
+    let abort = async {
+      tokio::task::yield_now().await;
+      controller.abort(Some("user".to_string()));
+    };
+    let (result, _) = tokio::join!(stream.pipe_to(&mut dest, options), abort);
+    let e = result.unwrap_err();
+    assert_eq!(AbortError::from_io(&e).unwrap().reason.as_deref(), Some("user"));
+    assert!(dest.write(Bytes::from("x")).await.is_err());
+  }
+}
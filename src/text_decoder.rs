@@ -0,0 +1,110 @@
+/// 从`Content-Type`中取出`charset`参数，返回小写的编码标签
+pub(crate) fn charset(content_type: &str) -> Option<String> {
+  content_type.split(';').skip(1).find_map(|param| {
+    let (name, value) = param.split_once('=')?;
+    if !name.trim().eq_ignore_ascii_case("charset") {
+      return None;
+    }
+    let value = value.trim().trim_matches('"').trim();
+    (!value.is_empty()).then(|| value.to_ascii_lowercase())
+  })
+}
+
+/// 按照Encoding规范把正文解码为字符串
+///
+/// 先根据BOM判断编码，没有BOM时使用`Content-Type`中的`charset`，都没有时使用UTF-8，
+/// 无法解码的字节会被替换为U+FFFD
+pub(crate) fn decode(bytes: &[u8], content_type: Option<&str>) -> String {
+  if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+    return String::from_utf8_lossy(rest).to_string();
+  }
+  if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+    return decode_utf16(rest, u16::from_be_bytes);
+  }
+  if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+    return decode_utf16(rest, u16::from_le_bytes);
+  }
+  match content_type.and_then(charset).as_deref() {
+    Some("utf-16be") | Some("unicodefffe") => decode_utf16(bytes, u16::from_be_bytes),
+    Some("utf-16le") | Some("utf-16") | Some("unicode") | Some("ucs-2") => decode_utf16(bytes, u16::from_le_bytes),
+    Some(label) => decode_label(bytes, label),
+    None => String::from_utf8_lossy(bytes).to_string(),
+  }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+  let units = bytes.chunks(2).map(|pair| match pair {
+    [a, b] => from_bytes([*a, *b]),
+    _ => 0xFFFD,
+  });
+  char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+}
+
+#[cfg(feature = "encoding")]
+fn decode_label(bytes: &[u8], label: &str) -> String {
+  let encoding = encoding_rs::Encoding::for_label(label.as_bytes()).unwrap_or(encoding_rs::UTF_8);
+  encoding.decode_without_bom_handling(bytes).0.to_string()
+}
+
+/// windows-1252中0x80到0x9F对应的字符，其余字节与Latin-1相同
+#[cfg(not(feature = "encoding"))]
+const WINDOWS_1252: [char; 32] = [
+  '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+  '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+  '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+  '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+/// 没有`encoding`特性时只支持UTF-8和windows-1252；按Encoding Standard，Latin-1和ASCII的标签也使用windows-1252
+#[cfg(not(feature = "encoding"))]
+fn decode_label(bytes: &[u8], label: &str) -> String {
+  match label {
+    "windows-1252" | "cp1252" | "x-cp1252" | "iso-8859-1" | "iso8859-1" | "iso88591" | "iso_8859-1"
+    | "latin1" | "l1" | "cp819" | "ibm819" | "us-ascii" | "ascii" => bytes.iter().map(|&b| match b {
+      0x80..=0x9F => WINDOWS_1252[(b - 0x80) as usize],
+      _ => b as char,
+    }).collect(),
+    _ => String::from_utf8_lossy(bytes).to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_charset() {
+    assert_eq!(charset("text/html; Charset=\"Shift_JIS\""), Some("shift_jis".to_string()));
+    assert_eq!(charset("text/plain;format=flowed;charset=utf-8"), Some("utf-8".to_string()));
+    assert_eq!(charset("text/plain"), None);
+  }
+
+  #[test]
+  fn windows_1252() {
+    for charset in ["iso-8859-1", "latin1", "us-ascii", "windows-1252", "cp1252"] {
+      let content_type = format!("text/plain; charset={}", charset);
+      assert_eq!(decode(b"\x80 caf\xE9", Some(&content_type)), "\u{20AC} caf\u{E9}");
+    }
+  }
+
+  #[test]
+  fn bom_wins_over_charset() {
+    assert_eq!(decode(b"\xEF\xBB\xBFhi", Some("text/plain; charset=utf-16le")), "hi");
+    assert_eq!(decode(b"\xFF\xFEh\x00i\x00", None), "hi");
+    assert_eq!(decode(b"\xFE\xFF\x00h\x00i", None), "hi");
+  }
+
+  #[test]
+  fn multibyte_and_invalid() {
+    assert_eq!(decode("你好".as_bytes(), None), "你好");
+    assert_eq!(decode(b"a\xFFb", None), "a\u{FFFD}b");
+  }
+
+  #[cfg(feature = "encoding")]
+  #[test]
+  fn legacy_encodings() {
+    assert_eq!(decode(b"\x82\xb1\x82\xf1", Some("text/plain; charset=Shift_JIS")), "こん");
+    assert_eq!(decode(b"\xc4\xe3\xba\xc3", Some("text/plain; charset=gbk")), "你好");
+    assert_eq!(decode(b"caf\xe9 \x80", Some("text/plain; charset=windows-1252")), "café €");
+  }
+}
@@ -0,0 +1,87 @@
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use crate::blob::Blob;
+use super::URL;
+
+/// 进程内的blob URL表，键为不带片段的`blob:` URL
+static OBJECT_URLS: Mutex<BTreeMap<String, Blob>> = Mutex::new(BTreeMap::new());
+
+/// 生成UUID格式的标识，进程内不会重复，但不是RFC 4122的随机UUID，不能当作无法猜测的令牌
+fn unique_id() -> String {
+  static COUNTER: AtomicU64 = AtomicU64::new(0);
+  let random = || {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+  };
+  let (high, low) = (random(), random());
+  format!(
+    "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+    high >> 32,
+    high >> 16 & 0xffff,
+    high & 0xffff,
+    low >> 48,
+    low & 0xffff_ffff_ffff,
+  )
+}
+
+impl URL {
+  /// 为`Blob`创建`blob:null/<id>`形式的URL，对应JS中的`URL.createObjectURL`
+  ///
+  /// URL在调用`revoke_object_url`之前一直有效，`fetch`可以直接读取其中的数据；
+  /// URL中的标识只保证不重复，可能被同一进程中的代码猜到
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::blob::Blob;
+  /// use fetch_js::url::URL;
+  ///
+  /// let url = URL::create_object_url(&Blob::new("hello", "text/plain"));
+  /// assert!(url.starts_with("blob:null/"));
+  /// URL::revoke_object_url(&url);
+  /// ```
+  pub fn create_object_url(blob: &Blob) -> String {
+    Self::create_object_url_with_origin(blob, "null")
+  }
+
+  /// 与`create_object_url`相同，URL中使用指定的源，如`https://example.com`
+  pub fn create_object_url_with_origin(blob: &Blob, origin: &str) -> String {
+    let url = format!("blob:{}/{}", origin, unique_id());
+    OBJECT_URLS.lock().unwrap().insert(url.clone(), blob.clone());
+    url
+  }
+
+  /// 移除`create_object_url`创建的URL，之后无法再通过它读取数据
+  pub fn revoke_object_url(url: &str) {
+    OBJECT_URLS.lock().unwrap().remove(&URL::new(url).get_href_without_hash());
+  }
+}
+
+/// 查找blob URL对应的`Blob`，片段会被忽略
+#[cfg_attr(not(feature = "tokio-fetch"), allow(dead_code))]
+pub(crate) fn resolve_object_url(url: &URL) -> Option<Blob> {
+  OBJECT_URLS.lock().unwrap().get(&url.get_href_without_hash()).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn object_urls() {
+    let blob = Blob::new("hello", "text/plain");
+    let url = URL::create_object_url_with_origin(&blob, "https://example.com");
+    assert!(url.starts_with("blob:https://example.com/"));
+    assert_eq!(url.len(), "blob:https://example.com/".len() + 36);
+    assert_ne!(url, URL::create_object_url(&blob));
+
+    let parsed = URL::new(&format!("{}#frag", url));
+    assert_eq!(parsed.get_origin(), "https://example.com");
+    assert_eq!(resolve_object_url(&parsed), Some(blob));
+    URL::revoke_object_url(&url);
+    assert_eq!(resolve_object_url(&parsed), None);
+  }
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::Display;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct URLSearchParams {
   params: HashMap<String, String>,
 }
@@ -25,8 +25,8 @@ impl URLSearchParams {
     };
     let mut params = HashMap::new();
     for param in param_strings {
-      let parts = param.split('=').collect::<Vec<&str>>();
-      params.insert(parts[0].to_string(), parts[1].to_string());
+      let (key, value) = param.split_once('=').unwrap_or((param, ""));
+      params.insert(key.to_string(), value.to_string());
     };
     Self {
       params,
@@ -35,6 +35,32 @@ impl URLSearchParams {
 }
 
 impl URLSearchParams {
+  /// 按`application/x-www-form-urlencoded`格式解析，保留顺序和重复的键，
+  /// `+`会被解码为空格，`%XX`转义会被解码
+  ///
+  /// # Example
+  /// ```
+  /// use fetch_js::url::URLSearchParams;
+  /// let pairs = URLSearchParams::parse("?a=1&a=2&q=hello+w%C3%B6rld&flag");
+  /// assert_eq!(pairs, [
+  ///   ("a".to_string(), "1".to_string()),
+  ///   ("a".to_string(), "2".to_string()),
+  ///   ("q".to_string(), "hello wörld".to_string()),
+  ///   ("flag".to_string(), "".to_string()),
+  /// ]);
+  /// ```
+  pub fn parse(input: &str) -> Vec<(String, String)> {
+    let decode = |s: &str| String::from_utf8_lossy(&crate::url::percent_decode(&s.replace('+', " "))).into_owned();
+    input.strip_prefix('?').unwrap_or(input)
+      .split('&')
+      .filter(|param| !param.is_empty())
+      .map(|param| {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        (decode(key), decode(value))
+      })
+      .collect()
+  }
+
   pub fn is_empty(&self) -> bool {
     self.params.is_empty()
   }